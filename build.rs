@@ -39,23 +39,6 @@ fn generate_completions() {
     generate(Shell::Zsh, &mut app, appname, &outdir, format!("zsh/_{}", appname));
 }
 
-fn convert_pkl_to_json() {
-    let output = std::process::Command::new("pkl")
-            .args(["eval", "-o", "testdata/config.json", "-f", "json", "testdata/config.pkl"])
-            .output();
-    match output {
-        Ok(o) => {
-            if !o.status.success() {
-                panic!("Failed to convert pkl to json: {:?}", o);
-            } else {
-                println!("Converted pkl to json");
-            }
-        }
-        Err(e) => panic!("{}", e),
-    }
-}
-
 fn main() {
     generate_completions();
-    convert_pkl_to_json();
 }
\ No newline at end of file
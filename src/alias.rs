@@ -1,10 +1,11 @@
-use clap::Parser;
-use std::iter::Iterator;
+use clap::{CommandFactory, Parser};
 use std::collections::HashMap;
+use std::env;
+use std::iter::Iterator;
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::config::{Config, Context};
 use crate::cli::{CliOpts, Result, RrhError};
 
 pub(crate) trait AliasManager {
@@ -13,6 +14,10 @@ pub(crate) trait AliasManager {
     fn register(&mut self, alias: Alias) -> Result<()>;
     fn update(&mut self, alias: Alias) -> Result<()>;
     fn delete(&mut self, alias_name: String) -> Result<()>;
+    /// the value of `${NAME}` in the aliases.
+    fn variable(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
 }
 
 impl AliasManager for Config {
@@ -35,6 +40,13 @@ impl AliasManager for Config {
     fn iterator(&self) -> impl Iterator<Item=Alias> {
         self.aliases.iterator()
     }
+
+    /// the settings of the config take precedence over the environment variables.
+    fn variable(&self, name: &str) -> Option<String> {
+        self.value(name.to_string())
+            .map(|v| v.to_string())
+            .or_else(|| env::var(name).ok())
+    }
 }
 
 impl AliasManager for HashMap<String, Vec<String>> {
@@ -81,16 +93,346 @@ pub(crate) struct Alias {
     pub(crate) commands: Vec<String>,
 }
 
+/// the separator running the next command only if the previous command succeeded.
+const AND_THEN: &str = "&&";
+/// the separator running the next command regardless of the result of the previous command.
+const THEN: &str = ";";
+
+/// a rrh command in the expansion of the alias.
+/// For the shell alias, `shell` is the alias name and the first element of `args` is the script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Step {
+    pub(crate) args: Vec<String>,
+    pub(crate) on_success: bool,
+    pub(crate) shell: Option<String>,
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.shell {
+            Some(_) => write!(f, "!{}", self.args.join(" ")),
+            None => write!(f, "rrh {}", self.args.join(" ")),
+        }
+    }
+}
+
 impl Alias {
     pub(crate) fn new(name: String, commands: Vec<String>) -> Self {
         Self { name, commands }
     }
 
-    pub(crate) fn execute(&self, args: Vec<String>) -> Result<()> {
-        let mut new_args = vec![String::from("rrh2")];
-        new_args.extend(self.commands.clone());
-        new_args.extend(args.clone());
-        crate::perform(CliOpts::parse_from(new_args))
+    /// returns the shell script of the shell alias, which starts with `!` like git.
+    pub(crate) fn shell_script(&self) -> Option<String> {
+        self.commands.join(" ")
+            .strip_prefix('!')
+            .map(|s| s.trim().to_string())
+    }
+
+    /// executes the expanded commands with the same config file of the given context.
+    pub(crate) fn execute(&self, context: &Context, args: Vec<String>) -> Result<()> {
+        let steps = match self.expand(&context.config, &args, true) {
+            Ok(steps) => steps,
+            Err(e) => return Err(e),
+        };
+        let mut errs = vec![];
+        let mut last_ok = true;
+        let mut reloaded: Option<Context> = None;
+        let mut stale = false;
+        for step in steps {
+            if step.on_success && !last_ok {
+                continue;
+            }
+            let result = match step.shell {
+                Some(name) => {
+                    if let (true, Some(from)) = (stale, &context.config.from) {
+                        reloaded = Context::new_with_path(from.clone()).ok();
+                        stale = false;
+                    }
+                    let c = reloaded.as_ref().unwrap_or(context);
+                    log::info!("alias {}: !{}", self.name, step.args.join(" "));
+                    crate::commands::perform_shell_alias(c, &name, step.args[0].clone(), step.args[1..].to_vec())
+                        .map(|_| ())
+                }
+                None => {
+                    stale = true;
+                    run_rrh(&self.name, &context.config, step.args)
+                }
+            };
+            last_ok = result.is_ok();
+            if let Err(e) = result {
+                errs.push(e);
+            }
+        }
+        match errs.len() {
+            0 => Ok(()),
+            1 => Err(errs.remove(0)),
+            _ => Err(RrhError::Arrays(errs)),
+        }
+    }
+
+    /// expands the placeholders (`$1`, `$@`, `${N:-default}`, and `${NAME:-default}`) by the given
+    /// arguments and the variables, and the aliases in the commands recursively.
+    /// If no placeholders are in the commands, the arguments are appended to the last command.
+    /// In the non-strict mode, the placeholders without values are left as they are.
+    pub(crate) fn expand(&self, manager: &impl AliasManager, args: &[String], strict: bool) -> Result<Vec<Step>> {
+        self.expand_impl(manager, args, strict, &mut vec![])
+    }
+
+    fn expand_impl(&self, manager: &impl AliasManager, args: &[String], strict: bool, stack: &mut Vec<String>) -> Result<Vec<Step>> {
+        if stack.contains(&self.name) {
+            let mut cycle = stack.clone();
+            cycle.push(self.name.clone());
+            return Err(RrhError::AliasCycle(cycle));
+        }
+        if let Some(script) = self.shell_script() {
+            let mut step_args = vec![script];
+            step_args.extend(args.iter().cloned());
+            return Ok(vec![Step { args: step_args, on_success: false, shell: Some(self.name.clone()) }]);
+        }
+        stack.push(self.name.clone());
+        let mut steps = vec![];
+        for (tokens, on_success) in split_commands(&self.commands) {
+            let mut step_args = vec![];
+            for token in tokens {
+                if token == "$@" {
+                    step_args.extend(args.iter().cloned());
+                    continue;
+                }
+                match substitute(&self.name, token, args, manager, strict) {
+                    Ok(s) => step_args.push(s),
+                    Err(e) => return Err(e),
+                }
+            }
+            steps.push(Step { args: step_args, on_success, shell: None });
+        }
+        if !self.commands.iter().any(|c| has_placeholder(c)) {
+            if let Some(last) = steps.last_mut() {
+                last.args.extend(args.iter().cloned());
+            }
+        }
+        let mut result = vec![];
+        for step in steps {
+            let nested = step.args.first()
+                .filter(|name| !is_builtin(name))
+                .and_then(|name| manager.find(name.clone()));
+            match nested {
+                Some(alias) => match alias.expand_impl(manager, &step.args[1..], strict, stack) {
+                    Ok(mut nested_steps) => {
+                        if let Some(first) = nested_steps.first_mut() {
+                            first.on_success = step.on_success;
+                        }
+                        result.extend(nested_steps);
+                    }
+                    Err(e) => return Err(e),
+                },
+                None => result.push(step),
+            }
+        }
+        stack.pop();
+        Ok(result)
+    }
+}
+
+/// runs the rrh command with the same config file of the given config.
+fn run_rrh(alias: &str, config: &Config, args: Vec<String>) -> Result<()> {
+    let mut new_args = vec![String::from("rrh2")];
+    if let Some(from) = &config.from {
+        new_args.push(String::from("--config-file"));
+        new_args.push(from.to_string_lossy().to_string());
+    }
+    new_args.extend(args);
+    log::info!("alias {}: {}", alias, new_args[1..].join(" "));
+    match CliOpts::try_parse_from(new_args) {
+        Ok(opts) => crate::perform(opts),
+        Err(e) => Err(RrhError::Arguments(e.to_string())),
+    }
+}
+
+fn is_builtin(name: &str) -> bool {
+    CliOpts::command().find_subcommand(name).is_some()
+}
+
+/// splits the commands by `&&` and `;`, the flag shows the command runs only on success.
+fn split_commands(commands: &[String]) -> Vec<(Vec<&String>, bool)> {
+    let mut result = vec![];
+    let mut current = vec![];
+    let mut on_success = false;
+    for token in commands {
+        if token == AND_THEN || token == THEN {
+            if !current.is_empty() {
+                result.push((current, on_success));
+            }
+            current = vec![];
+            on_success = token == AND_THEN;
+        } else {
+            current.push(token);
+        }
+    }
+    if !current.is_empty() {
+        result.push((current, on_success));
+    }
+    result
+}
+
+fn has_placeholder(token: &str) -> bool {
+    let chars = token.chars().collect::<Vec<_>>();
+    chars.windows(2).enumerate().any(|(i, w)| {
+        w[0] == '$' && (w[1] == '@' || w[1] == '{' || w[1].is_ascii_digit())
+            && (i == 0 || chars[i - 1] != '$')
+    })
+}
+
+fn substitute(alias: &str, token: &str, args: &[String], manager: &impl AliasManager, strict: bool) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = token;
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (original, name, default) = if let Some(r) = after.strip_prefix('$') {
+            result.push('$');
+            rest = r;
+            continue;
+        } else if let Some(r) = after.strip_prefix('@') {
+            result.push_str(&args.join(" "));
+            rest = r;
+            continue;
+        } else if after.starts_with('{') && after.contains('}') {
+            let end = after.find('}').unwrap();
+            let inner = &after[1..end];
+            rest = &after[end + 1..];
+            match inner.split_once(":-") {
+                Some((name, default)) => (&after[..=end], name, Some(default)),
+                None => (&after[..=end], inner, None),
+            }
+        } else {
+            let len = after.chars().take_while(|c| c.is_ascii_digit()).count();
+            if len == 0 {
+                result.push('$');
+                rest = after;
+                continue;
+            }
+            rest = &after[len..];
+            (&after[..len], &after[..len], None)
+        };
+        let value = match name.parse::<usize>() {
+            Ok(0) => Some(alias.to_string()),
+            Ok(n) => args.get(n - 1).cloned(),
+            Err(_) if name == "@" => Some(args.join(" ")),
+            Err(_) => manager.variable(name),
+        };
+        match (value, default) {
+            (Some(v), _) => result.push_str(&v),
+            (None, Some(d)) => result.push_str(d),
+            (None, None) if strict => return Err(RrhError::CliOptsInvalid(
+                "alias".into(),
+                format!("{}: no value for ${}", alias, original),
+            )),
+            (None, None) => {
+                result.push('$');
+                result.push_str(original);
+            }
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> HashMap<String, Vec<String>> {
+        let mut m = HashMap::new();
+        let mut add = |name: &str, commands: &[&str]| {
+            m.insert(name.to_string(), commands.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        };
+        add("hello", &["exec", "echo", "Hello, ${1:-world}!"]);
+        add("all", &["exec", "-g", "$1", "--", "git", "$@"]);
+        add("grlist", &["group", "list"]);
+        add("both", &["hello", "$1", "&&", "grlist", ";", "list"]);
+        add("cleanup", &["!git", "gc", "\"$@\""]);
+        add("tidy", &["grlist", "&&", "cleanup", "$1"]);
+        add("loop1", &["loop2"]);
+        add("loop2", &["loop1"]);
+        m
+    }
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let m = manager();
+        let hello = m.find("hello".into()).unwrap();
+        assert_eq!(hello.expand(&m, &[], true).unwrap()[0].args, strings(&["exec", "echo", "Hello, world!"]));
+        assert_eq!(hello.expand(&m, &strings(&["rrh"]), true).unwrap()[0].args, strings(&["exec", "echo", "Hello, rrh!"]));
+
+        let all = m.find("all".into()).unwrap();
+        let steps = all.expand(&m, &strings(&["g1", "status", "-s"]), true).unwrap();
+        assert_eq!(steps[0].args, strings(&["exec", "-g", "g1", "--", "git", "g1", "status", "-s"]));
+        assert!(all.expand(&m, &[], true).is_err());
+        assert_eq!(all.expand(&m, &[], false).unwrap()[0].args[2], "$1");
+    }
+
+    #[test]
+    fn test_config_variables() {
+        let config = Config::new_with_path("testdata/config.json".into()).unwrap();
+        assert_eq!(config.variable("default_group"), Some(String::from("no-group")));
+        assert!(config.variable("PATH").is_some());
+        assert_eq!(config.variable("PATH"), env::var("PATH").ok());
+    }
+
+    #[test]
+    fn test_append_without_placeholders() {
+        let m = manager();
+        let grlist = m.find("grlist".into()).unwrap();
+        assert_eq!(grlist.expand(&m, &strings(&["-N"]), true).unwrap()[0].args, strings(&["group", "list", "-N"]));
+    }
+
+    #[test]
+    fn test_chain_and_nested() {
+        let m = manager();
+        let both = m.find("both".into()).unwrap();
+        let steps = both.expand(&m, &strings(&["you"]), true).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].args, strings(&["exec", "echo", "Hello, you!"]));
+        assert_eq!(steps[1], Step { args: strings(&["group", "list"]), on_success: true, shell: None });
+        assert_eq!(steps[2], Step { args: strings(&["list"]), on_success: false, shell: None });
+    }
+
+    #[test]
+    fn test_shell_alias() {
+        let m = manager();
+        let cleanup = m.find("cleanup".into()).unwrap();
+        assert_eq!(cleanup.shell_script(), Some(String::from("git gc \"$@\"")));
+        let steps = cleanup.expand(&m, &strings(&["--prune=now"]), true).unwrap();
+        assert_eq!(steps, vec![Step { args: strings(&["git gc \"$@\"", "--prune=now"]), on_success: false, shell: Some("cleanup".into()) }]);
+
+        let tidy = m.find("tidy".into()).unwrap();
+        let steps = tidy.expand(&m, &strings(&["--quiet"]), true).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[1].args[1], "--quiet");
+        assert!(steps[1].on_success);
+        assert_eq!(steps[1].to_string(), "!git gc \"$@\" --quiet");
+    }
+
+    #[test]
+    fn test_cycle() {
+        let m = manager();
+        let loop1 = m.find("loop1".into()).unwrap();
+        match loop1.expand(&m, &[], true) {
+            Err(RrhError::AliasCycle(names)) => assert_eq!(names, strings(&["loop1", "loop2", "loop1"])),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        let m = manager();
+        assert_eq!(substitute("a", "$$1 costs $", &strings(&["x"]), &m, true).unwrap(), "$1 costs $");
+        assert!(!has_placeholder("$$1"));
+        assert!(has_placeholder("${HOME}"));
     }
 }
 
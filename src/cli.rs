@@ -1,4 +1,4 @@
-use clap::{Parser, ValueEnum};
+use clap::{ArgAction, Parser, ValueEnum};
 use std::{fmt::Display, path::PathBuf, process::ExitStatus};
 
 pub type Result<T> = std::result::Result<T, RrhError>;
@@ -11,14 +11,27 @@ pub enum RrhError {
     RepositoryExists(String),
     GroupExists(String),
     GroupNotEmpty(String),
+    GroupCycle(String, String),
     RepositoryPathNotFound(PathBuf),
     RepositoryAndGroupExists(String),
     RepositoryAndGroupNotFound(String),
+    AmbiguousRepository(String, Vec<String>),
+    ProfileNotFound(String),
+    ProfileExists(String),
+    ReadOnlyEntry(String, String),
+    AliasCycle(Vec<String>),
+    UnknownCommand(String, Vec<String>),
+    DatabaseLocked(PathBuf),
+    DatabaseModified(PathBuf),
+    ConfigReadOnly(PathBuf),
+    Selector(String, String),
     ToNameExist(String),
     CliOptsInvalid(String, String),
     Arrays(Vec<RrhError>),
     IO(std::io::Error),
     Json(serde_json::Error),
+    Toml(String),
+    Yaml(serde_yaml::Error),
     Git(git2::Error),
     Arguments(String),
     Fatal(String),
@@ -28,7 +41,65 @@ pub enum RrhError {
 
 impl Display for RrhError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        use RrhError::*;
+        match self {
+            IO(e) => write!(f, "IO error: {}", e),
+            Json(e) => write!(f, "JSON error: {}", e),
+            Toml(e) => write!(f, "TOML error: {}", e),
+            Yaml(e) => write!(f, "YAML error: {}", e),
+            Git(e) => write!(f, "Git error: {}", e),
+            Arguments(m) => write!(f, "arguments error: {}", m),
+            GroupNotFound(name) => write!(f, "{}: group not found", name),
+            RepositoryNotFound(name) => write!(f, "{}: repository not found", name),
+            RelationNotFound(id, group) => write!(f, "{}: relation not found for group {}", id, group),
+            CliOptsInvalid(command, message) => write!(f, "{}: {}", command, message),
+            RepositoryExists(name) => write!(f, "{}: repository already exists", name),
+            GroupExists(name) => write!(f, "{}: group already exists", name),
+            GroupNotEmpty(name) => write!(f, "{}: does not remove group since not empty", name),
+            GroupCycle(name, parent) => write!(f, "{}: cannot be moved under itself or its descendant {}", name, parent),
+            Fatal(message) => write!(f, "internal error: {}", message),
+            ExternalCommand(status, command) => write!(f, "{}: {}", command, status),
+            Unknown => write!(f, "unknown error"),
+            Arrays(v) => {
+                let messages = v.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", messages.join("\n"))
+            }
+            RepositoryPathNotFound(path) => write!(f, "{}: repository path not found", path.display()),
+            RepositoryAndGroupExists(name) => write!(f, "{}: repository and group both exists", name),
+            RepositoryAndGroupNotFound(name) => write!(f, "{}: no repository or group found", name),
+            ProfileNotFound(name) => write!(f, "{}: profile not found", name),
+            ProfileExists(name) => write!(f, "{}: profile already exists", name),
+            ReadOnlyEntry(name, layer) => write!(f, "{}: read-only entry of the {} database", name, layer),
+            AliasCycle(names) => write!(f, "alias cycle detected: {}", names.join(" -> ")),
+            UnknownCommand(name, suggestions) if suggestions.is_empty() => write!(f, "{}: unknown command", name),
+            UnknownCommand(name, suggestions) => {
+                write!(f, "{}: unknown command\ndid you mean {}?", name, suggestions.join(", "))
+            }
+            DatabaseLocked(path) => write!(f, "{}: the database is locked by another process", path.display()),
+            DatabaseModified(path) => write!(f, "{}: the database was modified by another process, run the command again", path.display()),
+            ConfigReadOnly(path) => write!(f, "{}: the Pkl config is not rewritten, edit the file directly", path.display()),
+            Selector(expr, message) => write!(f, "{}: invalid selector, {}", expr, message),
+            ToNameExist(name) => write!(f, "{}: the to name is occupied", name),
+            AmbiguousRepository(query, candidates) => {
+                write!(f, "{}: multiple repositories matched", query)?;
+                for candidate in candidates {
+                    write!(f, "\n    {}", candidate)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RrhError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RrhError::IO(e) => Some(e),
+            RrhError::Json(e) => Some(e),
+            RrhError::Yaml(e) => Some(e),
+            RrhError::Git(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
@@ -40,7 +111,7 @@ impl Display for RrhError {
     arg_required_else_help = true,
     allow_external_subcommands = true
 )]
-pub(crate) struct CliOpts {
+pub struct CliOpts {
     #[arg(
         long = "config-file",
         value_name = "FILE",
@@ -48,13 +119,30 @@ pub(crate) struct CliOpts {
     )]
     pub(crate) config_file: Option<PathBuf>,
 
-    #[arg(short, long, help = "Verbose mode")]
-    pub(crate) verbose: bool,
+    #[arg(
+        long = "profile",
+        value_name = "PROFILE",
+        help = "Use the given profile (default: $RRH_PROFILE or the current profile)"
+    )]
+    pub(crate) profile: Option<String>,
+
+    #[arg(short, long, action = ArgAction::Count, help = "Verbose mode (-v: info, -vv: debug, -vvv: trace)")]
+    pub(crate) verbose: u8,
+
+    #[arg(short, long, help = "Quiet mode (print only the errors)", conflicts_with = "verbose")]
+    pub(crate) quiet: bool,
+
+    #[arg(
+        long = "log-file",
+        value_name = "FILE",
+        help = "Append the diagnostics into the given file (default: $RRH_LOG_FILE)"
+    )]
+    pub(crate) log_file: Option<PathBuf>,
 
     #[clap(subcommand)]
     pub(crate) command: Option<RrhCommand>,
 
-    #[arg(index = 1, help = "arguments")]
+    #[arg(index = 1, help = "arguments", trailing_var_arg = true)]
     pub(crate) args: Vec<String>,
 }
 
@@ -89,6 +177,9 @@ pub(crate) enum RrhCommand {
     )]
     Clone(CloneOpts),
 
+    #[command(name = "config", about = "Show and edit the settings in the configuration file")]
+    Config(ConfigOpts),
+
     #[command(name = "find", about = "Find the repositories by the given keyword")]
     Find(FindOpts),
 
@@ -104,6 +195,9 @@ pub(crate) enum RrhCommand {
     #[command(name = "group", about = "Manage the groups for the rrh database")]
     Group(GroupOpts),
 
+    #[command(name = "import", about = "Import the repositories and groups from the exported database")]
+    Import(ImportOpts),
+
     #[command(
         name = "init",
         about = "Generate the shell functions for initializing rrh"
@@ -128,6 +222,49 @@ pub(crate) enum RrhCommand {
     )]
     Open(OpenOpts),
 
+    #[command(
+        name = "path",
+        alias = "jump",
+        about = "Print the path of the repository matched with the given query"
+    )]
+    Path(PathOpts),
+
+    #[command(
+        name = "pick",
+        about = "Choose the repositories interactively with the built-in fuzzy finder"
+    )]
+    Pick(PickOpts),
+
+    #[command(
+        name = "pin",
+        about = "Pin the repositories to show them first in list, recent and pick"
+    )]
+    Pin(PinOpts),
+
+    #[command(
+        name = "plugins",
+        about = "List the plugins (the rrh2-<name> or rrh-<name> commands on PATH)"
+    )]
+    Plugins(PluginsOpts),
+
+    #[command(
+        name = "tui",
+        about = "Browse and manage the groups and repositories in the terminal dashboard"
+    )]
+    Tui(TuiOpts),
+
+    #[command(
+        name = "profile",
+        about = "Manage the profiles, each has its own database and settings"
+    )]
+    Profile(ProfileOpts),
+
+    #[command(
+        name = "project",
+        about = "Manage the project metadata (language, build, test, docs, and owners) of the repositories"
+    )]
+    Project(ProjectOpts),
+
     #[command(
         name = "prune",
         about = "Prune the database (remove the non-existing repositories)"
@@ -143,8 +280,35 @@ pub(crate) enum RrhCommand {
     #[command(name = "recent", about = "List the recent updated repositories")]
     Recent(RecentOpts),
 
+    #[command(
+        name = "run",
+        about = "Run the build or test command declared in the project metadata of the repositories"
+    )]
+    Run(RunOpts),
+
+    #[command(name = "tag", about = "Manage the tags (KEY or KEY=VALUE) of the repositories")]
+    Tag(TagOpts),
+
+    #[command(name = "unpin", about = "Unpin the repositories")]
+    Unpin(PinOpts),
+
     #[command(name = "remove", about = "remove the repositories or groups from the database.")]
     Remove(RemoveOpts),
+
+    #[command(
+        name = "serve",
+        about = "Serve the database by JSON-RPC on a unix domain socket or a loopback TCP port"
+    )]
+    Serve(ServeOpts),
+
+    #[command(
+        name = "setup",
+        about = "Create the configuration file and the database (with the interactive wizard)"
+    )]
+    Setup(SetupOpts),
+
+    #[command(name = "__complete", hide = true, about = "Print the completion candidates for the shells")]
+    Complete(CompleteOpts),
 }
 
 #[derive(Parser, Debug)]
@@ -168,6 +332,7 @@ pub(crate) struct RepositoryOption {
     #[arg(
         short = 'r',
         long = "repository-id",
+        id = "new_id",
         value_name = "ID",
         help = "Specify the repository ID"
     )]
@@ -191,6 +356,27 @@ pub(crate) struct GroupSpecifier {
     pub(crate) group_names: Vec<String>,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct TagSpecifier {
+    #[arg(
+        long = "tag",
+        value_name = "TAGS",
+        value_delimiter = ',',
+        help = "select the repositories having all of the given tags (KEY or KEY=VALUE)"
+    )]
+    pub(crate) tags: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct SelectSpecifier {
+    #[arg(
+        long = "select",
+        value_name = "EXPR",
+        help = "select the repositories by the expression (e.g., \"group:work and not tag:archived\")"
+    )]
+    pub(crate) select: Option<String>,
+}
+
 #[derive(Parser, Debug)]
 pub(crate) struct RepositorySpecifier {
     #[arg(short, long, value_name = "REPO_IDS")]
@@ -205,6 +391,9 @@ pub(crate) struct AliasOpts {
     #[arg(short, long, help = "register repositories to the group.")]
     pub(crate) remove: bool,
 
+    #[arg(short, long, help = "print the expansion of the alias with the given arguments")]
+    pub(crate) show: bool,
+
     #[arg(help = "alias name", value_name = "ALIAS_NAME", index = 1)]
     pub(crate) alias: Option<String>,
 
@@ -236,6 +425,117 @@ pub(crate) struct CloneOpts {
     pub(crate) dry_run: bool,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct ConfigOpts {
+    #[clap(subcommand)]
+    pub(crate) subcmd: ConfigSubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum ConfigSubCommand {
+    #[command(name = "get", about = "Print the value of the given key")]
+    Get(ConfigGetOpts),
+
+    #[command(name = "set", about = "Set the value of the given key")]
+    Set(ConfigSetOpts),
+
+    #[command(name = "unset", about = "Remove the given key, and then the default value is used")]
+    Unset(ConfigUnsetOpts),
+
+    #[command(name = "list", about = "List the keys and their values")]
+    List(ConfigListOpts),
+
+    #[command(name = "edit", about = "Open the configuration file with $VISUAL or $EDITOR")]
+    Edit,
+
+    #[command(name = "path", about = "Print the path of the configuration file")]
+    Path,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ConfigGetOpts {
+    #[arg(index = 1, help = "specify the key", value_name = "KEY")]
+    pub(crate) key: String,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ConfigSetOpts {
+    #[arg(index = 1, help = "specify the key", value_name = "KEY")]
+    pub(crate) key: String,
+
+    #[arg(index = 2, help = "specify the value", value_name = "VALUE")]
+    pub(crate) value: String,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ConfigUnsetOpts {
+    #[arg(index = 1, help = "specify the key", value_name = "KEY")]
+    pub(crate) key: String,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ConfigListOpts {
+    #[arg(short, long, help = "print the descriptions of the keys")]
+    pub(crate) description: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ProfileOpts {
+    #[clap(subcommand)]
+    pub(crate) subcmd: ProfileSubCommand,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) enum ProfileSubCommand {
+    #[command(name = "list", about = "List the profiles (`*` marks the profile in use)")]
+    List,
+
+    #[command(name = "add", about = "Create the new profile")]
+    Add(ProfileAddOpts),
+
+    #[command(name = "use", about = "Change the current profile")]
+    Use(ProfileUseOpts),
+
+    #[command(name = "remove", about = "Remove the given profile")]
+    Remove(ProfileRemoveOpts),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ProfileAddOpts {
+    #[arg(short, long, help = "specify the database path (default: next to the config file of the profile)", value_name = "DB_FILE")]
+    pub(crate) database: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        help = "specify the profiles stacked under the profile as read-only databases",
+        value_name = "PROFILE",
+        use_value_delimiter = true
+    )]
+    pub(crate) stack: Vec<String>,
+
+    #[arg(short, long = "use", help = "change the current profile to the new one")]
+    pub(crate) use_it: bool,
+
+    #[arg(index = 1, id = "profile", help = "specify the profile name", value_name = "PROFILE")]
+    pub(crate) name: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ProfileUseOpts {
+    #[arg(index = 1, id = "profile", help = "specify the profile name (\"default\" for the default config)", value_name = "PROFILE")]
+    pub(crate) name: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct ProfileRemoveOpts {
+    #[arg(long = "with-database", help = "remove the database of the profile, too")]
+    pub(crate) with_database: bool,
+
+    #[arg(index = 1, id = "profile", help = "specify the profile name", value_name = "PROFILE")]
+    pub(crate) name: String,
+}
+
 #[derive(Parser, Debug)]
 pub(crate) struct ExecOpts {
     #[clap(
@@ -247,6 +547,12 @@ pub(crate) struct ExecOpts {
     #[clap(flatten, help = "specify the repositories for executing the commands")]
     pub(crate) repositories: RepositorySpecifier,
 
+    #[clap(flatten)]
+    pub(crate) tags: TagSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
     #[clap(long = "no-header", help = "do not show the header")]
     pub(crate) no_header: bool,
 
@@ -257,6 +563,126 @@ pub(crate) struct ExecOpts {
     pub(crate) arguments: Vec<String>,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct RunOpts {
+    #[arg(index = 1, value_enum, value_name = "TASK", help = "the task to run")]
+    pub(crate) task: ProjectTask,
+
+    #[clap(flatten)]
+    pub(crate) groups: GroupSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) repositories: RepositorySpecifier,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[clap(long = "no-header", help = "do not show the header")]
+    pub(crate) no_header: bool,
+
+    #[arg(index = 2, value_name = "ARGUMENTS", help = "the arguments appended to the command")]
+    pub(crate) arguments: Vec<String>,
+}
+
+#[derive(Debug, ValueEnum, PartialEq, Clone)]
+pub(crate) enum ProjectTask {
+    Build,
+    Test,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ProjectOpts {
+    #[clap(subcommand)]
+    pub(crate) subcmd: ProjectSubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum ProjectSubCommand {
+    #[command(name = "set", about = "Set the project metadata of the repository (the empty value clears it)")]
+    Set(ProjectSetOpts),
+    #[command(name = "sync", about = "Read .rrh.toml in the repositories, and cache it into the database")]
+    Sync(ProjectSyncOpts),
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ProjectSetOpts {
+    #[arg(long, value_name = "LANGUAGE", help = "the programming language")]
+    pub(crate) language: Option<String>,
+
+    #[arg(long, value_name = "COMMAND", help = "the build command")]
+    pub(crate) build: Option<String>,
+
+    #[arg(long, value_name = "COMMAND", help = "the test command")]
+    pub(crate) test: Option<String>,
+
+    #[arg(long, value_name = "URL", help = "the url of the documents")]
+    pub(crate) docs: Option<String>,
+
+    #[arg(long, value_name = "OWNERS", value_delimiter = ',', help = "the owners separated by commas")]
+    pub(crate) owners: Option<Vec<String>>,
+
+    #[arg(index = 1, value_name = "REPOSITORY_ID", required = true)]
+    pub(crate) repository_id: String,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ProjectSyncOpts {
+    #[arg(index = 1, value_name = "REPOSITORIES...", help = "the repositories to read (default: all)")]
+    pub(crate) ids: Vec<String>,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[arg(long = "dry-run", help = "dry-run mode")]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct TagOpts {
+    #[clap(subcommand)]
+    pub(crate) subcmd: TagSubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) enum TagSubCommand {
+    #[command(name = "add", about = "Add the tags to the repository (KEY=VALUE replaces the value of KEY)")]
+    Add(TagUpdateOpts),
+    #[command(name = "remove", about = "Remove the tags from the repository (KEY removes KEY=VALUE, too)")]
+    Remove(TagUpdateOpts),
+    #[command(name = "list", about = "List the tags and their repositories")]
+    List(TagListOpts),
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct PinOpts {
+    #[arg(index = 1, value_name = "REPO_IDS", required_unless_present = "select")]
+    pub(crate) repository_ids: Vec<String>,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[arg(long = "dry-run", help = "dry-run mode")]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct TagUpdateOpts {
+    #[arg(index = 1, value_name = "REPOSITORY_ID", required = true)]
+    pub(crate) repository_id: String,
+
+    #[arg(index = 2, value_name = "TAGS", required = true)]
+    pub(crate) tags: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct TagListOpts {
+    #[arg(index = 1, value_name = "REPOSITORIES...", help = "list the tags of the given repositories (default: all)")]
+    pub(crate) ids: Vec<String>,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+}
+
 #[derive(Parser, Debug)]
 pub(crate) struct ExportOpts {
     #[arg(
@@ -279,6 +705,21 @@ pub(crate) struct ExportOpts {
 
     #[arg(short, long, help = "indent the resultant json file")]
     pub(crate) indent: bool,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct ImportOpts {
+    #[arg(index = 1, help = "specify the exported database. \"-\" means stdin", value_name = "FILE", default_value = "-")]
+    pub(crate) src: String,
+
+    #[arg(short, long, help = "overwrite the existing repositories and groups")]
+    pub(crate) overwrite: bool,
+
+    #[arg(long = "dry-run", help = "dry-run mode")]
+    pub(crate) dry_run: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -292,9 +733,18 @@ pub(crate) struct FindOpts {
     #[arg(
         help = "keywords for finding the repositories",
         value_name = "KEYWORDS",
-        required = true
+        required_unless_present_any = ["tags", "select"]
     )]
     pub(crate) keywords: Vec<String>,
+
+    #[clap(flatten)]
+    pub(crate) tags: TagSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) p_opts: RepositoryPrintingOpts,
 }
 
 #[derive(Parser, Debug)]
@@ -317,6 +767,9 @@ pub(crate) enum GroupSubCommand {
     #[command(name = "remove", about = "Remove the groups from the rrh database")]
     Remove(GroupRemoveOpts),
 
+    #[command(name = "reorder", about = "Reorder the repositories in the group, the given ones come first")]
+    Reorder(GroupReorderOpts),
+
     #[command(name = "update", about = "Update the groups in the rrh database")]
     Update(GroupUpdateOpts),
 }
@@ -329,7 +782,14 @@ pub(crate) struct GroupAddOpts {
     #[arg(short, long, help = "specify the note of group", value_name = "NOTE")]
     pub(crate) note: Option<String>,
 
+    #[arg(short, long, help = "specify the parent group", value_name = "GROUP")]
+    pub(crate) parent: Option<String>,
+
+    #[arg(long, help = "make the smart groups with the selector expression (e.g., \"path:~/work/* or dirty\")", value_name = "EXPR")]
+    pub(crate) rule: Option<String>,
+
     #[arg(
+        id = "groups",
         help = "specify the group names",
         required = true,
         value_name = "GROUPS"
@@ -344,12 +804,16 @@ pub(crate) struct GroupAddOpts {
 pub(crate) struct GroupOfOpts {
     #[arg(
         index = 1,
+        id = "repository_ids",
         help = "specify the repository names for showing the groups",
         value_name = "REPOSITORIES...",
-        required = true
+        required_unless_present = "select"
     )]
     pub(crate) names: Vec<String>,
 
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
     #[clap(flatten)]
     pub(crate) p_opts: GroupPrintingOpts,
 }
@@ -359,7 +823,10 @@ pub(crate) struct GroupListOpts {
     #[clap(flatten)]
     pub(crate) p_opts: GroupPrintingOpts,
 
-    #[arg(index = 1, help = "listing target group names. if not given the value, print the all groups", value_name = "[GROUP_NAME...]")]
+    #[arg(short, long, help = "list the hidden groups, too")]
+    pub(crate) all: bool,
+
+    #[arg(index = 1, id = "groups", help = "listing target group names. if not given the value, print the all groups", value_name = "[GROUP_NAME...]")]
     pub(crate) args: Vec<String>,
 }
 
@@ -381,6 +848,9 @@ pub(crate) enum GroupEntry {
     Name,
     Abbrev,
     Note,
+    Parent,
+    Rule,
+    Settings,
     Count,
 }
 
@@ -389,13 +859,25 @@ pub(crate) struct GroupRemoveOpts {
     #[arg(short, long, help = "force remove the group")]
     pub(crate) force: bool,
 
-    #[arg(index = 1, help = "The target group names for removal", value_name = "GROUPS")]
+    #[arg(index = 1, id = "groups", help = "The target group names for removal", value_name = "GROUPS")]
     pub(crate) args: Vec<String>,
 
     #[arg(long = "dry-run", help = "dry-run mode")]
     pub(crate) dry_run: bool,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct GroupReorderOpts {
+    #[arg(index = 1, id = "group", help = "the target group name", value_name = "GROUP", required = true)]
+    pub(crate) name: String,
+
+    #[arg(index = 2, help = "the repositories in the new order", value_name = "REPO_IDS", required = true)]
+    pub(crate) repository_ids: Vec<String>,
+
+    #[arg(long = "dry-run", help = "dry-run mode")]
+    pub(crate) dry_run: bool,
+}
+
 #[derive(Parser, Debug)]
 pub(crate) struct GroupUpdateOpts {
     #[arg(
@@ -422,7 +904,58 @@ pub(crate) struct GroupUpdateOpts {
     )]
     pub(crate) rename_to: Option<String>,
 
-    #[arg(help = "specify the group name", required = true, value_name = "GROUP")]
+    #[arg(
+        short,
+        long,
+        help = "move the group and its descendants under the parent group (empty for the root)",
+        value_name = "GROUP"
+    )]
+    pub(crate) parent: Option<String>,
+
+    #[arg(
+        long,
+        help = "specify the selector expression of the smart group (empty for the plain group)",
+        value_name = "EXPR"
+    )]
+    pub(crate) rule: Option<String>,
+
+    #[arg(
+        long = "clone-dir",
+        help = "specify the directory for cloning the repositories into the group (empty for unset)",
+        value_name = "DIR"
+    )]
+    pub(crate) clone_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "specify the default shell command or shell alias of exec for the group (empty for unset)",
+        value_name = "COMMAND"
+    )]
+    pub(crate) exec: Option<String>,
+
+    #[arg(
+        long,
+        help = "specify the color of the group header in list (empty for unset)",
+        value_name = "COLOR"
+    )]
+    pub(crate) color: Option<String>,
+
+    #[arg(
+        long,
+        help = "specify the sort order of the group, the smaller comes first",
+        value_name = "ORDER",
+        allow_negative_numbers = true
+    )]
+    pub(crate) order: Option<i32>,
+
+    #[arg(
+        long,
+        help = "specify the hidden flag, the hidden groups are excluded from the listings",
+        value_name = "HIDDEN_FLAG"
+    )]
+    pub(crate) hidden: Option<bool>,
+
+    #[arg(id = "group", help = "specify the group name", required = true, value_name = "GROUP")]
     pub(crate) name: String,
 
     #[arg(long = "dry-run", help = "dry-run mode")]
@@ -440,6 +973,12 @@ pub(crate) struct InitOpts {
     #[arg(long, help = "not generate the rrhfzf function")]
     pub(crate) without_rrhfzf: bool,
 
+    #[arg(long, help = "not generate the rrhpick function")]
+    pub(crate) without_rrhpick: bool,
+
+    #[arg(long, help = "not generate the completion of rrh itself")]
+    pub(crate) without_completion: bool,
+
     #[arg(
         index = 1,
         value_name = "SHELL_NAME",
@@ -484,10 +1023,88 @@ pub(crate) struct OpenOpts {
     )]
     pub(crate) target: OpenTarget,
 
-    #[clap(value_name = "REPOSITORY/GROUP", required = true, index = 1, help = "specify the open target repositories or groups")]
+    #[clap(flatten)]
+    pub(crate) tags: TagSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[clap(id = "targets", value_name = "REPOSITORY/GROUP", required_unless_present_any = ["tags", "select"], index = 1, help = "specify the open target repositories or groups")]
     pub(crate) args: Vec<String>,
 }
 
+#[derive(Parser, Debug)]
+pub(crate) struct PathOpts {
+    #[arg(
+        index = 1,
+        help = "specify the repository id, its prefix, its fuzzy pattern, or GROUP/QUERY",
+        value_name = "QUERY"
+    )]
+    pub(crate) query: String,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct PickOpts {
+    #[arg(short, long, help = "allow to choose multiple repositories by Tab key", conflicts_with = "cd")]
+    pub(crate) multi: bool,
+
+    #[arg(long, help = "print \"cd <PATH>\" of the chosen repository for eval in the shell")]
+    pub(crate) cd: bool,
+
+    #[arg(short, long, value_name = "OUTPUT", help = "specify the printing entry of the chosen repositories",
+        value_enum, default_value_t = PickOutput::Path)]
+    pub(crate) output: PickOutput,
+
+    #[arg(short, long, value_name = "QUERY", help = "specify the initial query")]
+    pub(crate) query: Option<String>,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[arg(index = 1, value_name = "GROUPS", help = "specify the groups of the candidates. if not given, all repositories are candidates")]
+    pub(crate) groups: Vec<String>,
+}
+
+#[derive(Debug, ValueEnum, PartialEq, Clone)]
+pub(crate) enum PickOutput {
+    Id,
+    Path,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct TuiOpts {
+    #[arg(index = 1, value_name = "GROUP", help = "specify the initially selected group")]
+    pub(crate) group: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub(crate) struct PluginsOpts {
+    #[arg(long = "no-describe", help = "do not run the plugins with --rrh-describe")]
+    pub(crate) no_describe: bool,
+}
+
+#[derive(Parser, Debug)]
+#[group(required = true, multiple = false)]
+pub(crate) struct ServeOpts {
+    #[arg(short, long, value_name = "PATH", help = "serve on the unix domain socket of the given path")]
+    pub(crate) socket: Option<PathBuf>,
+
+    #[arg(short, long, value_name = "PORT", help = "serve on the given TCP port of 127.0.0.1 (0 picks a free port)")]
+    pub(crate) port: Option<u16>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub(crate) struct SetupOpts {
+    #[arg(short, long, help = "ask the clone directory, the default group and the list style")]
+    pub(crate) interactive: bool,
+
+    #[arg(short, long, help = "overwrite the existing configuration file (the database is kept)")]
+    pub(crate) force: bool,
+}
+
 #[derive(Debug, ValueEnum, PartialEq, Clone)]
 pub(crate) enum OpenTarget {
     Folder,
@@ -541,10 +1158,13 @@ pub(crate) struct RepositoryInfoOpts {
 
     #[arg(
         help = "specify the ids for the target repositories",
-        required = true,
+        required_unless_present = "select",
         value_name = "REPOSITORY_ID"
     )]
     pub(crate) ids: Vec<String>,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
 }
 
 #[derive(Parser, Debug)]
@@ -552,6 +1172,15 @@ pub(crate) struct RepositoryListOpts {
     #[clap(flatten)]
     pub(crate) p_opts: RepositoryPrintingOpts,
 
+    #[clap(flatten)]
+    pub(crate) tags: TagSpecifier,
+
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[arg(short, long, help = "list the repositories of the hidden groups, too")]
+    pub(crate) all: bool,
+
     #[arg(
         help = "specify the group names for listing the repositories",
         value_name = "GROUPS"
@@ -566,6 +1195,12 @@ pub(crate) enum RepositoryEntry {
     Groups,
     Description,
     LastAccess,
+    Language,
+    Build,
+    Test,
+    Docs,
+    Owners,
+    Tags,
     All,
 }
 
@@ -574,6 +1209,12 @@ pub(crate) struct RepositoryRemoveOpts {
     #[arg(index = 1, help = "specify the ids for the target repositories")]
     pub(crate) ids: Vec<String>,
 
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
+    #[arg(short, long, help = "remove the repositories matched with --select without the confirmation")]
+    pub(crate) yes: bool,
+
     #[arg(long = "dry-run", help = "dry-run mode")]
     pub(crate) dry_run: bool,
 }
@@ -638,6 +1279,9 @@ pub(crate) struct RecentOpts {
     )]
     pub(crate) number: Option<usize>,
 
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
     #[clap(flatten)]
     pub(crate) p_opts: RepositoryPrintingOpts,
 }
@@ -659,9 +1303,26 @@ pub(crate) struct RemoveOpts {
     #[arg(short, long, help = "force mode")]
     pub(crate) force: bool,
 
+    #[arg(short, long, help = "remove the repositories matched with --select without the confirmation")]
+    pub(crate) yes: bool,
+
     #[arg(long = "dry-run", help = "dry-run mode")]
     pub(crate) dry_run: bool,
 
+    #[clap(flatten)]
+    pub(crate) select: SelectSpecifier,
+
     #[arg(index = 1, help = "specify the target repository or group names", value_name = "TARGETS")]
     pub(crate) targets: Vec<String>,
 }
+
+#[derive(Parser, Debug)]
+pub(crate) struct CompleteOpts {
+    #[arg(
+        help = "the words of the command line up to the cursor",
+        value_name = "WORDS",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub(crate) words: Vec<String>,
+}
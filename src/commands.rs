@@ -1,14 +1,29 @@
+use std::path::PathBuf;
+
 use crate::cli::*;
 use crate::config::Context;
 
 mod add;
 mod alias;
+mod complete;
+mod config;
+mod export;
 mod group;
 mod init;
 mod list;
 mod exec;
+mod path;
+mod pick;
+mod pin;
+mod plugins;
+mod profile;
+mod project;
 mod prune;
 mod repository;
+mod serve;
+mod setup;
+mod tag;
+mod tui;
 
 pub fn perform_add(context: &mut Context, c: AddOpts) -> Result<bool> {
     add::perform_add(context, c)
@@ -22,8 +37,24 @@ pub fn perform_clone(context: &mut Context, c: CloneOpts) -> Result<bool> {
     add::perform_clone(context, c)
 }
 
+pub fn perform_complete(context: &Context, c: CompleteOpts) -> Result<bool> {
+    complete::perform_complete(context, c)
+}
+
+pub fn perform_config(context: &mut Context, c: ConfigOpts) -> Result<bool> {
+    config::perform(context, c)
+}
+
+pub fn perform_shell_alias(context: &Context, name: &str, script: String, args: Vec<String>) -> Result<bool> {
+    exec::perform_shell_alias(context, name, script, args)
+}
+
+pub fn select_repositories(context: &Context, args: Vec<String>) -> Result<(Option<Vec<crate::entities::Repository>>, Vec<String>)> {
+    exec::select_repositories(context, args)
+}
+
 pub fn perform_find(context: &Context, c: FindOpts) -> Result<bool> {
-    todo!();
+    list::perform_find(context, c)
 }
 
 pub fn perform_exec(context: &mut Context, c: ExecOpts) -> Result<bool> {
@@ -31,13 +62,17 @@ pub fn perform_exec(context: &mut Context, c: ExecOpts) -> Result<bool> {
 }
 
 pub fn perform_export(context: &mut Context, c: ExportOpts) -> Result<bool> {
-    todo!();
+    export::perform_export(context, c)
 }
 
 pub fn perform_group(context: &mut Context, c: GroupOpts) -> Result<bool> {
     group::perform(context, c)
 }
 
+pub fn perform_import(context: &mut Context, c: ImportOpts) -> Result<bool> {
+    export::perform_import(context, c)
+}
+
 pub fn perform_init(context: &mut Context, c: InitOpts) -> Result<bool> {
     init::perform(context, c)
 }
@@ -50,6 +85,38 @@ pub fn perform_open(context: &mut Context, c: OpenOpts) -> Result<bool> {
     exec::perform_open(context, c)
 }
 
+pub fn perform_path(context: &Context, c: PathOpts) -> Result<bool> {
+    path::perform_path(context, c)
+}
+
+pub fn perform_pin(context: &mut Context, c: PinOpts, pinned: bool) -> Result<bool> {
+    pin::perform_pin(context, c, pinned)
+}
+
+pub fn perform_plugins(context: &Context, c: PluginsOpts) -> Result<bool> {
+    plugins::perform(context, c)
+}
+
+pub fn perform_run(context: &mut Context, c: RunOpts) -> Result<bool> {
+    exec::perform_run(context, c)
+}
+
+pub fn perform_serve(config_file: Option<PathBuf>, profile: Option<String>, c: ServeOpts) -> Result<bool> {
+    serve::perform(config_file, profile, c)
+}
+
+pub fn perform_pick(context: &Context, c: PickOpts) -> Result<bool> {
+    pick::perform_pick(context, c)
+}
+
+pub fn perform_profile(profile: Option<String>, c: ProfileOpts) -> Result<bool> {
+    profile::perform(profile, c)
+}
+
+pub fn perform_project(context: &mut Context, c: ProjectOpts) -> Result<bool> {
+    project::perform(context, c)
+}
+
 pub fn perform_prune(context: &mut Context, c: PruneOpts) -> Result<bool> {
     prune::perform_prune(context, c)
 }
@@ -66,6 +133,18 @@ pub fn perform_rename(context: &mut Context, c: RenameOpts) -> Result<bool> {
     prune::perform_rename(context, c)
 }
 
+pub fn perform_setup(config_file: Option<PathBuf>, c: SetupOpts) -> Result<bool> {
+    setup::perform_setup(config_file, c)
+}
+
+pub fn perform_tag(context: &mut Context, c: TagOpts) -> Result<bool> {
+    tag::perform(context, c)
+}
+
+pub fn perform_tui(context: &mut Context, c: TuiOpts) -> Result<bool> {
+    tui::perform_tui(context, c)
+}
+
 pub fn perform_remove(context: &mut Context, c: RemoveOpts) -> Result<bool> {
     prune::perform_remove(context, c)
 }
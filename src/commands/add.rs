@@ -1,9 +1,10 @@
 use std::path::PathBuf;
 
-use crate::db::Database;
+use crate::commands::repository;
 use crate::entities::Repository;
-use crate::config::Context;
+use crate::config::{Config, Context};
 use crate::cli::{AddOpts, CloneOpts, Result, RrhError, RepositoryOption};
+use crate::{logger, utils};
 
 pub fn perform_add(context: &mut Context, c: AddOpts) -> Result<bool> {
     let mut errs = vec![];
@@ -11,7 +12,7 @@ pub fn perform_add(context: &mut Context, c: AddOpts) -> Result<bool> {
         match build_repository_from_path(path, &c.repo) {
             Err(e) => errs.push(e),
             Ok(r) => {
-                if let Err(e) = register_repository(&mut context.db, r, c.repo.groups.group_names.clone()) {
+                if let Err(e) = register_repository(context, r, c.repo.groups.group_names.clone()) {
                     errs.push(e);
                 }
             }
@@ -24,8 +25,21 @@ pub fn perform_add(context: &mut Context, c: AddOpts) -> Result<bool> {
     }
 }
 
-fn register_repository(db: &mut Box<dyn Database>, r: Repository, groups: Vec<String>) -> Result<()> {
-    match db.register(r, groups) {
+/// registers the repository to the given groups, or to the default group if no groups are given.
+fn register_repository(context: &mut Context, r: Repository, groups: Vec<String>) -> Result<()> {
+    let groups = if groups.is_empty() {
+        context.config.value_or_default("default_group")
+            .map(|v| vec![v.to_string()])
+            .unwrap_or_default()
+    } else {
+        groups
+    };
+    for name in groups.iter() {
+        if let Err(e) = repository::ensure_group(context, name) {
+            return Err(e);
+        }
+    }
+    match context.db.register(r, groups) {
         Ok(()) => Ok(()),
         Err(e) => Err(e), 
     }
@@ -43,7 +57,11 @@ fn build_repository_from_path(path: PathBuf, c: &RepositoryOption) -> Result<Rep
         Ok(id) => id,
         Err(e) => return Err(e),
     };
-    Ok(Repository::new(id, path, c.description.clone()))
+    let mut repo = Repository::new(id, path, c.description.clone());
+    if let Err(e) = repo.refresh_project() {
+        log::warn!("{}: {}", repo.id, e);
+    }
+    Ok(repo)
 }
 
 fn find_id(path: &PathBuf, repository_id: Option<String>) -> Result<String> {
@@ -58,7 +76,9 @@ fn find_id(path: &PathBuf, repository_id: Option<String>) -> Result<String> {
 }
 
 impl CloneOpts {
-    fn repo_path(&self) -> PathBuf {
+    /// returns the destination of clone. Without the output directory, the repository is
+    /// cloned into the clone directory of the given groups, or `clone_directory` of the config.
+    fn repo_path(&self, context: &Context) -> PathBuf {
         let repo_name = self.repo_url.split('/').last().unwrap();
         let repo_path = if repo_name.ends_with(".git") {
             repo_name[..repo_name.len() - 4].to_string()
@@ -68,21 +88,30 @@ impl CloneOpts {
         if let Some(dest_dir) = &self.dest_dir {
             dest_dir.clone()
         } else {
-            PathBuf::from(".").join(repo_path)
+            let base = clone_directory(&context.config);
+            match group_clone_directory(context, &self.repo.groups.group_names) {
+                Some(dir) => base.join(dir).join(repo_path),
+                None => base.join(repo_path),
+            }
         }
     }
 }
 
 pub fn perform_clone(context: &mut Context, c: CloneOpts) -> Result<bool> {
-    let repo = match git2::Repository::clone(&c.repo_url.clone(), c.repo_path()) {
+    let dest = c.repo_path(context);
+    let label = format!("git clone: {} into {}", c.repo_url, dest.display());
+    let repo = match logger::timed(&label, || git2::Repository::clone(&c.repo_url, &dest)) {
         Ok(r) => r,
         Err(e) => return Err(RrhError::Git(e))
     };
-    let path = repo.path().to_path_buf();
+    let path = match repo.workdir() {
+        Some(p) => p.to_path_buf(),
+        None => repo.path().to_path_buf(),
+    };
     match build_repository_from_path(path, &c.repo) {
         Err(e) => Err(e),
         Ok(r) => {
-            match register_repository(&mut context.db, r, c.repo.groups.group_names.clone()) {
+            match register_repository(context, r, c.repo.groups.group_names.clone()) {
                 Err(e) => Err(e),
                 Ok(_) => Ok(!c.dry_run),
             }
@@ -90,11 +119,60 @@ pub fn perform_clone(context: &mut Context, c: CloneOpts) -> Result<bool> {
     }
 }
 
+/// returns the clone directory of the first group having it (or its ancestors).
+/// The relative directories are resolved against `clone_directory` of the config.
+fn group_clone_directory(context: &Context, group_names: &[String]) -> Option<PathBuf> {
+    group_names.iter()
+        .flat_map(|name| context.db.find_group(name).into_iter().chain(context.db.find_ancestors(name)))
+        .find_map(|g| g.clone_dir)
+        .map(|dir| utils::expand_home(&dir))
+}
+
+fn clone_directory(config: &Config) -> PathBuf {
+    match config.value_or_default("clone_directory") {
+        Some(dir) => utils::expand_home(&dir.to_string()),
+        None => PathBuf::from("."),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::commands::GroupSpecifier;
 
+    #[test]
+    fn test_add_to_default_group() {
+        let mut context = Context::new_with_path("testdata/config.json".into()).unwrap();
+        let _ = context.db.delete_group(String::from("no-group"));
+        let opts = AddOpts {
+            paths: vec![PathBuf::from("testdata/fibonacci")],
+            dry_run: false,
+            repo: RepositoryOption { repository_id: Some("fibonacci2".into()), groups: GroupSpecifier{ group_names: vec![] }, description: None },
+        };
+        let result = perform_add(&mut context, opts);
+        assert!(result.is_ok());
+        assert!(context.db.find_group("no-group").is_some());
+        assert!(context.db.has_relation("fibonacci2", "no-group"));
+    }
+
+    #[test]
+    fn test_clone_dir_of_group() {
+        let mut context = Context::new_with_path("testdata/config.json".into()).unwrap();
+        let mut group = context.db.find_group("no-group").unwrap();
+        group.clone_dir = Some(String::from("/tmp/work"));
+        context.db.update_group(group.name.clone(), group).unwrap();
+        let mut child = crate::entities::Group::new("child".into());
+        child.parent = Some(String::from("no-group"));
+        context.db.register_group(child).unwrap();
+        let clone_opts = CloneOpts {
+            repo_url: "https://github.com/tamada/helloworld.git".into(),
+            dest_dir: None,
+            dry_run: false,
+            repo: RepositoryOption {  repository_id: None, groups: GroupSpecifier{ group_names: vec!["child".into()] }, description: None },
+        };
+        assert_eq!(clone_opts.repo_path(&context), PathBuf::from("/tmp/work/helloworld"));
+    }
+
     #[test]
     fn test_clone() {
         let mut context = Context::new_with_path("testdata/config.json".into()).unwrap();
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::alias::{Alias, AliasManager};
 use crate::cli::{AliasOpts, Result, RrhError};
 use crate::config::Context;
@@ -7,6 +9,7 @@ pub(crate) enum Mode {
     Register,
     Upadte,
     Remove,
+    Show,
     Execute,
 }
 
@@ -16,6 +19,8 @@ impl AliasOpts {
             Mode::Upadte
         } else if self.remove {
             Mode::Remove
+        } else if self.show {
+            Mode::Show
         } else if self.arguments.len() == 0 {
             Mode::List
         } else {
@@ -25,17 +30,17 @@ impl AliasOpts {
 
     pub(crate) fn validate(&self, context: &Context) -> Result<Mode> {
         let mut errs = vec![];
-        if self.update && self.remove {
+        if [self.update, self.remove, self.show].iter().filter(|b| **b).count() > 1 {
             errs.push(RrhError::CliOptsInvalid(
                 "alias".into(),
-                "Cannot update and remove at the same time".into(),
+                "Cannot update, remove, and show at the same time".into(),
             ));
         }
         let mode = self.find_mode();
         match &mode {
             Mode::Register => validate_register(self, &context.config, &mut errs),
             Mode::Upadte => validate_update(self, &context.config, &mut errs),
-            Mode::Remove => validate_remove(self, &context.config, &mut errs),
+            Mode::Remove | Mode::Show => validate_remove(self, &context.config, &mut errs),
             Mode::Execute | Mode::List => {}
         }
         if errs.len() == 0 {
@@ -57,11 +62,12 @@ impl AliasOpts {
 }
 
 fn validate_remove(c: &AliasOpts, manager: &impl AliasManager, errs: &mut Vec<RrhError>) {
-    validate_alias_name(c.alias.clone(), errs, "alias_remove".into());
+    let command = if c.show { "alias_show" } else { "alias_remove" };
+    validate_alias_name(c.alias.clone(), errs, command.into());
     if let Some(alias) = &c.alias {
         if manager.find(alias.clone()).is_none() {
             errs.push(RrhError::CliOptsInvalid(
-                "alias_remove".into(),
+                command.into(),
                 format!("{}: alias not found", alias),
             ))
         }
@@ -114,8 +120,40 @@ fn perform_list(manager: &impl AliasManager) -> Result<bool> {
     Ok(false)
 }
 
+/// the cycle is detected by expanding the new alias among the existing aliases.
+fn validate_cycle(manager: &impl AliasManager, alias: &Alias) -> Result<()> {
+    let mut aliases = manager.iterator()
+        .map(|a| (a.name, a.commands))
+        .collect::<HashMap<String, Vec<String>>>();
+    aliases.insert(alias.name.clone(), alias.commands.clone());
+    alias.expand(&aliases, &[], false).map(|_| ())
+}
+
+fn perform_show(manager: &impl AliasManager, c: AliasOpts) -> Result<bool> {
+    let alias = match c.alias.and_then(|name| manager.find(name)) {
+        Some(a) => a,
+        None => unreachable!("unreachable since already check in validate_remove"),
+    };
+    match alias.expand(manager, &c.arguments, false) {
+        Ok(steps) => {
+            for (i, step) in steps.iter().enumerate() {
+                match (i, step.on_success) {
+                    (0, _) => println!("{}", step),
+                    (_, true) => println!("  && {}", step),
+                    (_, false) => println!("  ; {}", step),
+                }
+            }
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 fn perform_register(manager: &mut impl AliasManager, c: AliasOpts) -> Result<bool> {
     if let Some(alias) = c.build_alias() {
+        if let Err(e) = validate_cycle(manager, &alias) {
+            return Err(e);
+        }
         match manager.register(alias) {
             Ok(_) => Ok(true),
             Err(e) => Err(e),
@@ -127,6 +165,9 @@ fn perform_register(manager: &mut impl AliasManager, c: AliasOpts) -> Result<boo
 
 fn perform_update(manager: &mut impl AliasManager, c: AliasOpts) -> Result<bool> {
     if let Some(alias) = c.build_alias() {
+        if let Err(e) = validate_cycle(manager, &alias) {
+            return Err(e);
+        }
         match manager.update(alias) {
             Ok(_) => Ok(true),
             Err(e) => Err(e),
@@ -155,7 +196,26 @@ pub fn perform(context: &mut Context, c: AliasOpts) -> Result<bool> {
             Mode::Register => perform_register(&mut context.config, c),
             Mode::Upadte => perform_update(&mut context.config, c),
             Mode::Remove => perform_remove(&mut context.config, c),
+            Mode::Show => perform_show(&context.config, c),
             Mode::Execute => unreachable!("Mode::Execute is never reach here!"),
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_cycle() {
+        let mut manager = HashMap::new();
+        manager.insert(String::from("a"), vec![String::from("b")]);
+        let opts = AliasOpts {
+            update: false, remove: false, show: false,
+            alias: Some(String::from("b")),
+            arguments: vec![String::from("list"), String::from("&&"), String::from("a")],
+        };
+        assert!(matches!(perform_register(&mut manager, opts), Err(RrhError::AliasCycle(_))));
+        assert!(manager.find(String::from("b")).is_none());
+    }
+}
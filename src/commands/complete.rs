@@ -0,0 +1,266 @@
+use clap::{Arg, Command, CommandFactory};
+use itertools::Itertools;
+
+use crate::alias::AliasManager;
+use crate::cli::{CliOpts, CompleteOpts, Result};
+use crate::config::{keys, profile, Context};
+use crate::external;
+
+/// The protocol of hidden `__complete` subcommand is as follows.
+///   rrh __complete -- <WORDS...>
+/// WORDS are the words of the command line up to the cursor (the first word is the program name,
+/// and the last word is the word under completion, it may be the empty string).
+/// The candidates are printed one per line. No output means the shell should fall back to its
+/// default (file name) completion.
+pub(crate) fn perform_complete(context: &Context, c: CompleteOpts) -> Result<bool> {
+    for candidate in complete(context, &c.words) {
+        println!("{}", candidate);
+    }
+    Ok(false)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    Subcommands,
+    Options,
+    Repositories,
+    Groups,
+    RepositoriesOrGroups,
+    Aliases,
+    ConfigKeys,
+    Profiles,
+    Tags,
+    Values(Vec<String>),
+    Nothing,
+}
+
+fn complete(context: &Context, words: &[String]) -> Vec<String> {
+    let (current, preceding) = match words.split_last() {
+        Some((last, rest)) => (last.clone(), rest),
+        None => (String::new(), words),
+    };
+    let preceding = if preceding.is_empty() { preceding } else { &preceding[1..] };
+    let mut root = CliOpts::command();
+    root.build();
+    let (cmd, kind, delimited) = find_kind(&root, preceding, &current);
+    let (head, prefix) = match (delimited, current.rfind(',')) {
+        (true, Some(i)) => (current[..=i].to_string(), current[i + 1..].to_string()),
+        _ => (String::new(), current.clone()),
+    };
+    let mut candidates = candidates_of(context, &cmd, kind, cmd.get_name() == root.get_name());
+    candidates.retain(|c| c.starts_with(&prefix));
+    candidates.sort();
+    candidates.dedup();
+    candidates.into_iter().map(|c| format!("{}{}", head, c)).collect()
+}
+
+fn find_kind(root: &Command, words: &[String], current: &str) -> (Command, Kind, bool) {
+    let mut cmd = root.clone();
+    let mut positionals = 0;
+    let mut pending: Option<Arg> = None;
+    let mut after_double_dash = false;
+    for w in words {
+        if pending.take().is_some() {
+            continue;
+        }
+        if w == "--" && !after_double_dash {
+            after_double_dash = true;
+            continue;
+        }
+        if !after_double_dash && w.starts_with('-') && w.len() > 1 {
+            if !w.contains('=') {
+                pending = find_option(&cmd, w).filter(|a| a.get_action().takes_values()).cloned();
+            }
+            continue;
+        }
+        if positionals == 0 && !after_double_dash {
+            if let Some(sub) = cmd.find_subcommand(w) {
+                cmd = sub.clone();
+                continue;
+            }
+        }
+        positionals += 1;
+    }
+    if let Some(arg) = pending {
+        let delimited = arg.get_value_delimiter().is_some();
+        return (cmd, kind_of(&arg), delimited);
+    }
+    if current.starts_with('-') && !after_double_dash {
+        return (cmd, Kind::Options, false);
+    }
+    if cmd.has_subcommands() && positionals == 0 {
+        return (cmd, Kind::Subcommands, false);
+    }
+    let positional = cmd.get_positionals()
+        .filter(|a| !a.is_hide_set())
+        .enumerate()
+        .find(|(i, a)| *i == positionals || (*i < positionals && is_multiple(a)))
+        .map(|(_, a)| a.clone());
+    match positional {
+        Some(arg) => {
+            let kind = kind_of(&arg);
+            (cmd, kind, false)
+        }
+        None => (cmd, Kind::Nothing, false),
+    }
+}
+
+fn find_option<'a>(cmd: &'a Command, word: &str) -> Option<&'a Arg> {
+    cmd.get_arguments().find(|a| {
+        if let Some(long) = word.strip_prefix("--") {
+            a.get_long() == Some(long) || a.get_all_aliases().map(|v| v.contains(&long)).unwrap_or(false)
+        } else {
+            word.len() == 2 && a.get_short() == word.chars().nth(1)
+        }
+    })
+}
+
+fn is_multiple(arg: &Arg) -> bool {
+    arg.get_num_args().map(|r| r.max_values() > 1).unwrap_or(false)
+}
+
+/// returns the completion kind of the argument by its id, the ids of the arguments in the cli
+/// follow the names in this function (e.g., `id = "groups"` for the group names).
+fn kind_of(arg: &Arg) -> Kind {
+    let values = arg.get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<_>>();
+    if !values.is_empty() {
+        return Kind::Values(values);
+    }
+    match arg.get_id().as_str() {
+        "repository_id" | "repository_ids" | "ids" | "query" => Kind::Repositories,
+        "group" | "groups" | "group_names" | "new_groups" | "parent" => Kind::Groups,
+        "targets" | "from" => Kind::RepositoriesOrGroups,
+        "alias" => Kind::Aliases,
+        "key" => Kind::ConfigKeys,
+        "profile" | "stack" => Kind::Profiles,
+        "tags" => Kind::Tags,
+        _ => Kind::Nothing,
+    }
+}
+
+fn candidates_of(context: &Context, cmd: &Command, kind: Kind, is_root: bool) -> Vec<String> {
+    match kind {
+        Kind::Subcommands => {
+            let mut result = cmd.get_subcommands()
+                .filter(|s| !s.is_hide_set())
+                .map(|s| s.get_name().to_string())
+                .collect::<Vec<_>>();
+            if is_root {
+                result.extend(aliases(context));
+                result.extend(external::find_plugins().into_iter().map(|p| p.name));
+            }
+            result
+        }
+        Kind::Options => cmd.get_arguments()
+            .filter(|a| !a.is_hide_set())
+            .filter_map(|a| a.get_long().map(|l| format!("--{}", l)))
+            .collect(),
+        Kind::Repositories => repositories(context),
+        Kind::Groups => groups(context),
+        Kind::RepositoriesOrGroups => {
+            let mut result = repositories(context);
+            result.extend(groups(context));
+            result
+        }
+        Kind::Aliases => aliases(context),
+        Kind::ConfigKeys => keys::KEYS.iter().map(|k| k.name.to_string()).collect(),
+        Kind::Profiles => {
+            let mut result = vec![String::from(profile::DEFAULT_PROFILE)];
+            result.extend(profile::names());
+            result
+        }
+        Kind::Tags => context.db.repositories()
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|r| r.tags)
+            .unique()
+            .collect(),
+        Kind::Values(values) => values,
+        Kind::Nothing => vec![],
+    }
+}
+
+fn repositories(context: &Context) -> Vec<String> {
+    context.db.repositories()
+        .map(|rs| rs.into_iter().map(|r| r.id).collect())
+        .unwrap_or_default()
+}
+
+fn groups(context: &Context) -> Vec<String> {
+    context.db.groups()
+        .map(|gs| gs.into_iter().map(|g| g.name).collect())
+        .unwrap_or_default()
+}
+
+fn aliases(context: &Context) -> Vec<String> {
+    context.config.iterator().map(|a| a.name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn run(words: &[&str]) -> Vec<String> {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let words = words.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        complete(&context, &words)
+    }
+
+    #[test]
+    fn test_complete_subcommands() {
+        let r = run(&["rrh", "gr"]);
+        assert_eq!(r, vec!["grlist", "group"]);
+        assert_eq!(run(&["rrh", "group", "l"]), vec!["list"]);
+    }
+
+    #[test]
+    fn test_complete_positionals() {
+        assert_eq!(run(&["rrh", "open", "f"]), vec!["fibonacci"]);
+        assert_eq!(run(&["rrh", "open", "fibonacci", "n"]), vec!["no-group"]);
+        assert_eq!(run(&["rrh", "group", "list", ""]), vec!["no-group"]);
+        assert_eq!(run(&["rrh", "init", "f"]), vec!["fish"]);
+        assert!(run(&["rrh", "add", ""]).is_empty());
+        assert!(run(&["rrh", "pin", ""]).contains(&String::from("fibonacci")));
+        assert!(run(&["rrh", "unpin", ""]).contains(&String::from("fibonacci")));
+        assert_eq!(run(&["rrh", "group", "reorder", ""]), vec!["no-group"]);
+        assert!(run(&["rrh", "group", "reorder", "no-group", ""]).contains(&String::from("fibonacci")));
+        assert!(run(&["rrh", "repository", "remove", ""]).contains(&String::from("fibonacci")));
+        assert_eq!(run(&["rrh", "group", "of", "h"]), vec!["helloworld"]);
+    }
+
+    #[test]
+    fn test_complete_options() {
+        assert_eq!(run(&["rrh", "exec", "-g", ""]), vec!["no-group"]);
+        assert_eq!(run(&["rrh", "repository", "update", "-g", "a,no"]), vec!["a,no-group"]);
+        assert!(run(&["rrh", "list", "--"]).contains(&String::from("--entries")));
+        assert!(run(&["rrh", "add", "--repository-id", ""]).is_empty());
+    }
+
+    #[test]
+    fn test_every_positional_completes() {
+        // the positionals taking the free words (paths, urls, commands, and new names).
+        let free = [
+            "rrh args", "rrh add paths", "rrh alias arguments", "rrh clone repo_url", "rrh config set value",
+            "rrh find keywords", "rrh exec arguments", "rrh import src", "rrh rename to_name",
+            "rrh repository add paths", "rrh run arguments", "rrh __complete words",
+        ];
+        fn walk(cmd: &Command, path: String, free: &[&str]) {
+            for arg in cmd.get_positionals().filter(|a| a.get_action().takes_values()) {
+                let name = format!("{} {}", path, arg.get_id());
+                assert_eq!(kind_of(arg) == Kind::Nothing, free.contains(&name.as_str()), "{}", name);
+            }
+            for sub in cmd.get_subcommands() {
+                walk(sub, format!("{} {}", path, sub.get_name()), free);
+            }
+        }
+        let mut root = CliOpts::command();
+        root.build();
+        walk(&root, String::from("rrh"), &free);
+    }
+}
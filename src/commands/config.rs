@@ -0,0 +1,154 @@
+use std::process::Command;
+
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+use crate::cli::{ConfigGetOpts, ConfigListOpts, ConfigOpts, ConfigSetOpts, ConfigSubCommand, ConfigUnsetOpts, Result, RrhError};
+use crate::config::{keys, Config, Context, EnvValue};
+
+pub(crate) fn perform(c: &mut Context, opts: ConfigOpts) -> Result<bool> {
+    match opts.subcmd {
+        ConfigSubCommand::Get(opts) => perform_get(&c.config, opts),
+        ConfigSubCommand::Set(opts) => perform_set(&mut c.config, opts).and_then(|r| c.store_config().map(|_| r)),
+        ConfigSubCommand::Unset(opts) => perform_unset(&mut c.config, opts).and_then(|r| c.store_config().map(|_| r)),
+        ConfigSubCommand::List(opts) => perform_list(&c.config, opts),
+        ConfigSubCommand::Edit => perform_edit(&c.config),
+        ConfigSubCommand::Path => perform_path(&c.config),
+    }
+}
+
+fn perform_get(config: &Config, opts: ConfigGetOpts) -> Result<bool> {
+    match (config.get_env(&opts.key), keys::find_key(&opts.key)) {
+        (Some(v), key) => {
+            warn_unknown_key(&opts.key, key.is_none());
+            println!("{}", v);
+            Ok(false)
+        }
+        (None, Some(key)) => {
+            println!("{}", key.default_value());
+            Ok(false)
+        }
+        (None, None) => Err(RrhError::CliOptsInvalid(
+            "config_get".into(),
+            format!("{}: unknown key", opts.key),
+        )),
+    }
+}
+
+fn perform_set(config: &mut Config, opts: ConfigSetOpts) -> Result<bool> {
+    let value = match keys::find_key(&opts.key) {
+        Some(key) => match key.parse(&opts.value) {
+            Ok(v) => v,
+            Err(e) => return Err(e),
+        },
+        None => return Err(RrhError::CliOptsInvalid(
+            "config_set".into(),
+            format!("{}: unknown key", opts.key),
+        )),
+    };
+    config.envs.insert(opts.key, value);
+    Ok(false)
+}
+
+fn perform_unset(config: &mut Config, opts: ConfigUnsetOpts) -> Result<bool> {
+    match config.envs.remove(&opts.key) {
+        Some(_) => Ok(false),
+        None => Err(RrhError::CliOptsInvalid(
+            "config_unset".into(),
+            format!("{}: key not set", opts.key),
+        )),
+    }
+}
+
+fn perform_list(config: &Config, opts: ConfigListOpts) -> Result<bool> {
+    let mut builder = Builder::new();
+    let mut header = vec!["Key", "Value", "Default"];
+    if opts.description {
+        header.push("Description");
+    }
+    builder.push_record(header);
+    for key in keys::KEYS {
+        let value = config.get_env(key.name).map(|v| v.to_string()).unwrap_or_default();
+        let mut row = vec![key.name.to_string(), value, key.default_value().to_string()];
+        if opts.description {
+            row.push(key.description.to_string());
+        }
+        builder.push_record(row);
+    }
+    let mut unknowns = config.envs.iter()
+        .filter(|(name, _)| keys::find_key(name).is_none())
+        .collect::<Vec<(&String, &EnvValue)>>();
+    unknowns.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in unknowns.iter() {
+        let mut row = vec![name.to_string(), value.to_string(), String::new()];
+        if opts.description {
+            row.push(String::from("(unknown key)"));
+        }
+        builder.push_record(row);
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    println!("{}", table);
+    for (name, _) in unknowns {
+        warn_unknown_key(name, true);
+    }
+    Ok(false)
+}
+
+fn perform_edit(config: &Config) -> Result<bool> {
+    let path = match &config.from {
+        Some(p) => p.clone(),
+        None => return Err(RrhError::Fatal("config path was not set".into())),
+    };
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or(String::from("vi"));
+    match Command::new(&editor).arg(&path).status() {
+        Ok(s) if s.success() => Ok(false),
+        Ok(s) => Err(RrhError::ExternalCommand(s, format!("{} {}", editor, path.display()))),
+        Err(e) => Err(RrhError::IO(e)),
+    }
+}
+
+fn perform_path(config: &Config) -> Result<bool> {
+    match &config.from {
+        Some(p) => {
+            println!("{}", p.display());
+            Ok(false)
+        }
+        None => Err(RrhError::Fatal("config path was not set".into())),
+    }
+}
+
+fn warn_unknown_key(name: &str, unknown: bool) {
+    if unknown {
+        log::warn!("{}: unknown config key", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_set_and_unset() {
+        let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let opts = ConfigSetOpts { key: String::from("last_access_reload_duration_secs"), value: String::from("60") };
+        assert!(!perform_set(&mut c.config, opts).unwrap());
+        assert!(matches!(c.config.get_env("last_access_reload_duration_secs"), Some(EnvValue::Value(60))));
+        assert!(c.config.is_modified());
+
+        let opts = ConfigSetOpts { key: String::from("auto_create_group"), value: String::from("sometimes") };
+        assert!(perform_set(&mut c.config, opts).is_err());
+
+        let opts = ConfigSetOpts { key: String::from("auto_create_grop"), value: String::from("true") };
+        assert!(perform_set(&mut c.config, opts).is_err());
+        assert!(c.config.get_env("auto_create_grop").is_none());
+
+        let opts = ConfigUnsetOpts { key: String::from("sort_on_update") };
+        assert!(!perform_unset(&mut c.config, opts).unwrap());
+        assert!(c.config.get_env("sort_on_update").is_none());
+    }
+}
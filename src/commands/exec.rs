@@ -2,26 +2,60 @@ use git_url_parse::GitUrl;
 use std::path::PathBuf;
 use std::process::{Command, ExitStatus};
 
-use crate::cli::{ExecOpts, OpenOpts, OpenTarget, Result, RrhError};
+use crate::alias::AliasManager;
+use crate::cli::{ExecOpts, OpenOpts, OpenTarget, ProjectTask, Result, RrhError, RunOpts};
 use crate::config::Context;
-use crate::entities::Repository;
+use crate::entities::{Project, Repository};
+use crate::selector::{self, Selector};
 
+/// runs the commands in the target repositories. Without the commands, the default command
+/// of the first given group having it (or its ancestors) is run.
 pub fn perform_exec(context: &Context, c: ExecOpts) -> Result<bool> {
-    if c.arguments.len() == 0 {
-        return Err(RrhError::Arguments(String::from(
-            "(exec) no commands are given",
-        )));
-    }
-    match find_target_repositories(
+    let shell = if c.arguments.is_empty() {
+        match default_command_of(context, &c.groups.group_names) {
+            Some(cmd) => Some(cmd),
+            None => return Err(RrhError::Arguments(String::from(
+                "(exec) no commands are given",
+            ))),
+        }
+    } else {
+        context.config.find(c.arguments[0].clone())
+            .and_then(|a| a.shell_script().map(|script| ShellCmd { name: a.name, script, args: c.arguments[1..].to_vec() }))
+    };
+    let selector = match Selector::from_options(&c.tags.tags, c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let no_targets = c.groups.group_names.is_empty() && c.repositories.repository_ids.is_empty();
+    let repos = find_target_repositories(
         context,
         c.groups.group_names.clone(),
         c.repositories.repository_ids.clone(),
-    ) {
-        Ok(repos) => perform_impl(c, repos),
+    ).and_then(|repos| selector::narrow(context.db.as_ref(), repos, no_targets, selector.as_ref()));
+    match repos {
+        Ok(repos) if repos.is_empty() && selector.is_some() => Err(RrhError::Arguments(String::from(
+            "(exec) no repositories match the given selection",
+        ))),
+        Ok(repos) => match shell {
+            Some(cmd) => perform_shell_impl(context, &cmd, c.no_header, repos),
+            None => perform_impl(c, repos),
+        },
         Err(e) => Err(e),
     }
 }
 
+/// returns the default command of the groups, which is the shell alias of the name,
+/// or the script run by `sh -c` (`$0` is the group name) to keep its quotes.
+fn default_command_of(context: &Context, group_names: &[String]) -> Option<ShellCmd> {
+    let (name, exec) = group_names.iter()
+        .flat_map(|name| context.db.find_group(name).into_iter().chain(context.db.find_ancestors(name)))
+        .find_map(|g| g.exec.map(|exec| (g.name, exec)))?;
+    match context.config.find(exec.clone()).and_then(|a| a.shell_script().map(|script| (a.name, script))) {
+        Some((alias, script)) => Some(ShellCmd { name: alias, script, args: vec![] }),
+        None => Some(ShellCmd { name, script: exec, args: vec![] }),
+    }
+}
+
 fn perform_impl(c: ExecOpts, repos: Vec<Repository>) -> Result<bool> {
     let command = Cmd::new(c.arguments);
     if repos.len() == 0 {
@@ -62,6 +96,7 @@ impl Cmd {
     }
 
     fn execute(&self, dir: PathBuf) -> Result<i32> {
+        log::info!("{}: {} {}", dir.display(), self.cmd, self.args.join(" "));
         match Command::new(self.cmd.clone())
             .args(self.args.clone())
             .current_dir(dir)
@@ -98,11 +133,231 @@ fn execute_command(cmd: &Cmd, repo: Repository) -> Result<bool> {
         .and_then(|p| cmd.execute(p).map(|_| false))
 }
 
+// ================ functions for run ================
+
+/// runs the command of the task declared in the project metadata of each repository.
+/// The metadata is refreshed by `.rrh.toml` of the repositories before running.
+pub(crate) fn perform_run(context: &mut Context, c: RunOpts) -> Result<bool> {
+    let no_targets = c.groups.group_names.is_empty() && c.repositories.repository_ids.is_empty();
+    if no_targets && c.select.select.is_none() {
+        return Err(RrhError::Arguments(String::from(
+            "(run) no repositories are given (-g, -r, or --select)",
+        )));
+    }
+    let selector = match Selector::from_options(&[], c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let repos = match find_target_repositories(
+        context,
+        c.groups.group_names.clone(),
+        c.repositories.repository_ids.clone(),
+    ).and_then(|repos| selector::narrow(context.db.as_ref(), repos, no_targets, selector.as_ref())) {
+        Ok(repos) => repos,
+        Err(e) => return Err(e),
+    };
+    let mut updated = false;
+    let mut errs = vec![];
+    for mut repo in repos {
+        match repo.refresh_project() {
+            Ok(true) => match context.db.update_repository(repo.id.clone(), repo.clone()) {
+                Ok(_) => updated = true,
+                Err(RrhError::ReadOnlyEntry(_, _)) => {}
+                Err(e) => errs.push(e),
+            },
+            Ok(false) => {}
+            Err(e) => {
+                errs.push(e);
+                continue;
+            }
+        }
+        match c.task.command_of(&repo.project) {
+            Some(command) => {
+                print_header(c.no_header, &repo);
+                if let Err(e) = run_task(&command, &repo, &c.arguments) {
+                    errs.push(e);
+                }
+            }
+            None => log::warn!("{}: no {} command declared", repo.id, c.task.name()),
+        }
+    }
+    if errs.is_empty() {
+        Ok(updated)
+    } else {
+        Err(RrhError::Arrays(errs))
+    }
+}
+
+impl ProjectTask {
+    fn name(&self) -> &'static str {
+        match self {
+            ProjectTask::Build => "build",
+            ProjectTask::Test => "test",
+        }
+    }
+
+    fn command_of(&self, project: &Project) -> Option<String> {
+        match self {
+            ProjectTask::Build => project.build.clone(),
+            ProjectTask::Test => project.test.clone(),
+        }
+    }
+}
+
+/// runs the declared command by `sh -c` in the repository, and appends the given arguments.
+fn run_task(command: &str, repo: &Repository, args: &[String]) -> Result<()> {
+    log::info!("{}: {} {}", repo.path.display(), command, args.join(" "));
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", command))
+        .arg(&repo.id)
+        .args(args)
+        .current_dir(&repo.path)
+        .status();
+    match status {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(RrhError::ExternalCommand(s, format!("{}: {}", repo.id, command))),
+        Err(e) => Err(RrhError::IO(e)),
+    }
+}
+
+// ================ functions for shell aliases ================
+
+/// runs the shell alias (the alias starting with `!`) once.
+/// The leading `-g GROUP`, `-r REPO_ID` and `--select EXPR` in the arguments select the repositories,
+/// which are given to the script by the environment variables.
+pub(crate) fn perform_shell_alias(context: &Context, name: &str, script: String, args: Vec<String>) -> Result<bool> {
+    match select_repositories(context, args) {
+        Ok((repos, args)) => {
+            let cmd = ShellCmd { name: name.to_string(), script, args };
+            cmd.execute(context, &repos.unwrap_or_default(), None).map(|_| false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// selects the repositories by the leading `-g GROUP`, `-r REPO_ID` and `--select EXPR` of the arguments,
+/// and returns `None` as the repositories if no selections were given.
+pub(crate) fn select_repositories(context: &Context, args: Vec<String>) -> Result<(Option<Vec<Repository>>, Vec<String>)> {
+    let (targets, args) = split_targets(args);
+    if targets.groups.is_empty() && targets.repo_ids.is_empty() && targets.select.is_none() {
+        return Ok((None, args));
+    }
+    let selector = match Selector::from_options(&[], targets.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let no_targets = targets.groups.is_empty() && targets.repo_ids.is_empty();
+    match find_target_repositories(context, targets.groups, targets.repo_ids)
+        .and_then(|repos| selector::narrow(context.db.as_ref(), repos, no_targets, selector.as_ref())) {
+        Ok(repos) => Ok((Some(repos), args)),
+        Err(e) => Err(e),
+    }
+}
+
+/// runs the shell alias in each repository by `rrh exec`.
+fn perform_shell_impl(context: &Context, cmd: &ShellCmd, no_header: bool, repos: Vec<Repository>) -> Result<bool> {
+    if repos.is_empty() {
+        return cmd.execute(context, &repos, None).map(|_| false);
+    }
+    let mut errs = vec![];
+    for repo in repos.iter() {
+        print_header(no_header, repo);
+        if let Err(e) = cmd.execute(context, &repos, Some(repo)) {
+            errs.push(e);
+        }
+    }
+    if errs.is_empty() {
+        Ok(false)
+    } else {
+        Err(RrhError::Arrays(errs))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Targets {
+    groups: Vec<String>,
+    repo_ids: Vec<String>,
+    select: Option<String>,
+}
+
+/// splits the leading options selecting the repositories (`-g`, `--group-names`, `-r`,
+/// `--repository-ids`, and `--select`) from the arguments. `--` stops the splitting.
+fn split_targets(args: Vec<String>) -> (Targets, Vec<String>) {
+    let mut targets = Targets::default();
+    let mut iter = args.into_iter().peekable();
+    while let Some(arg) = iter.peek().cloned() {
+        match arg.as_str() {
+            "-g" | "--group-names" | "-r" | "--repository-ids" | "--select" => {}
+            "--" => {
+                iter.next();
+                break;
+            }
+            _ => break,
+        };
+        iter.next();
+        let value = match iter.next() {
+            Some(value) => value,
+            None => break,
+        };
+        match arg.as_str() {
+            "-g" | "--group-names" => targets.groups.push(value),
+            "-r" | "--repository-ids" => targets.repo_ids.push(value),
+            _ => targets.select = Some(value),
+        }
+    }
+    (targets, iter.collect())
+}
+
+struct ShellCmd {
+    name: String,
+    script: String,
+    args: Vec<String>,
+}
+
+impl ShellCmd {
+    /// runs the script by `sh -c`, `$0` is the alias name and `$1`... are the arguments.
+    /// `RRH_REPOSITORIES` and `RRH_REPOSITORY_IDS` are the selected repositories separated by new lines,
+    /// and `RRH_REPOSITORY_ID` and `RRH_REPOSITORY_PATH` are the current repository of `rrh exec`.
+    fn execute(&self, context: &Context, repos: &[Repository], current: Option<&Repository>) -> Result<()> {
+        let paths = repos.iter()
+            .map(|r| r.path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let ids = repos.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.script).arg(&self.name).args(&self.args)
+            .env("RRH_ALIAS", &self.name)
+            .env("RRH_DATABASE", &context.config.database_path)
+            .env("RRH_REPOSITORIES", paths.join("\n"))
+            .env("RRH_REPOSITORY_IDS", ids.join("\n"));
+        if let Some(from) = &context.config.from {
+            command.env("RRH_CONFIG", from);
+        }
+        if let Some(repo) = current {
+            command.current_dir(&repo.path)
+                .env("RRH_REPOSITORY_ID", &repo.id)
+                .env("RRH_REPOSITORY_PATH", &repo.path);
+        }
+        log::info!("!{}: {} {}", self.name, self.script, self.args.join(" "));
+        match command.status() {
+            Ok(s) if s.success() => Ok(()),
+            Ok(s) => Err(RrhError::ExternalCommand(s, format!("!{}", self.name))),
+            Err(e) => Err(RrhError::IO(e)),
+        }
+    }
+}
+
 // ================ functions for open command ================
 
 pub fn perform_open(context: &Context, c: OpenOpts) -> Result<bool> {
     let target = c.target;
-    match find_open_targets(context, c.args.clone()) {
+    let selector = match Selector::from_options(&c.tags.tags, c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let repos = find_open_targets(context, c.args.clone())
+        .and_then(|repos| selector::narrow(context.db.as_ref(), repos, c.args.is_empty(), selector.as_ref()));
+    match repos {
         Ok(repos) => {
             if repos.len() == 0 {
                 Err(RrhError::Arguments(String::from(
@@ -121,7 +376,7 @@ fn find_open_targets(context: &Context, repos_or_groups: Vec<String>) -> Result<
     let mut errs = vec![];
     for group in repos_or_groups.clone() {
         if let Some(g) = context.db.find_group(&group) {
-            match context.db.find_repositories_of(&g.name) {
+            match context.db.find_repositories_under(&g.name) {
                 Ok(rs) => result.extend(rs),
                 Err(e) => errs.push(e),
             }
@@ -174,7 +429,7 @@ fn find_remote<'a>(repo: &'a git2::Repository) -> Result<git2::Remote<'a>> {
     repo.find_remote("origin").map_err(|e| RrhError::Git(e))
 }
 
-fn open_repository(target: &OpenTarget, repo: &Repository) -> Result<bool> {
+pub(crate) fn open_repository(target: &OpenTarget, repo: &Repository) -> Result<bool> {
     let path = repo.path.canonicalize().map_err(RrhError::IO)?;
     match target {
         OpenTarget::Folder => open_path(path),
@@ -249,7 +504,7 @@ fn find_target_repositories(
     let mut result = vec![];
     let mut errs = vec![];
     for group in groups {
-        match context.db.find_repositories_of(&group) {
+        match context.db.find_repositories_under(&group) {
             Ok(rs) => result.extend(rs),
             Err(e) => errs.push(e),
         }
@@ -270,6 +525,68 @@ fn find_target_repositories(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::TempDir;
+
+    #[test]
+    fn test_split_targets() {
+        let args = ["-g", "team", "-r", "rrh", "--select", "dirty", "--", "-g", "x"].iter().map(|s| s.to_string()).collect();
+        let (targets, rest) = split_targets(args);
+        assert_eq!(targets.groups, vec!["team"]);
+        assert_eq!(targets.repo_ids, vec!["rrh"]);
+        assert_eq!(targets.select, Some(String::from("dirty")));
+        assert_eq!(rest, vec!["-g", "x"]);
+    }
+
+    #[test]
+    fn test_shell_alias_env() {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let script = String::from("test \"$RRH_REPOSITORY_IDS\" = \"fibonacci\" && test \"$RRH_DATABASE\" = testdata/database.json && test \"$1\" = arg");
+        let args = ["-r", "fibonacci", "arg"].iter().map(|s| s.to_string()).collect();
+        assert!(perform_shell_alias(&context, "check", script, args).is_ok());
+        assert!(perform_shell_alias(&context, "fail", String::from("exit 3"), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_default_command_of() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut group = context.db.find_group("no-group").unwrap();
+        group.exec = Some(String::from("test \"$0 $RRH_REPOSITORY_ID\" = 'no-group fibonacci'"));
+        context.db.update_group(group.name.clone(), group).unwrap();
+        let cmd = default_command_of(&context, &[String::from("no-group")]).unwrap();
+        assert_eq!(cmd.name, "no-group");
+        let repo = context.db.find_repository("fibonacci").unwrap();
+        assert!(cmd.execute(&context, &[], Some(&repo)).is_ok());
+        assert!(default_command_of(&context, &[String::from("unknown")]).is_none());
+    }
+
+    #[test]
+    fn test_perform_run() {
+        let dir = TempDir::new("run");
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        for (id, toml) in [("passing", Some("test = \"test arg =\"\n")), ("failing", Some("test = \"exit 3\"\n")), ("bare", None)] {
+            let path = dir.join(id);
+            std::fs::create_dir_all(&path).unwrap();
+            if let Some(toml) = toml {
+                std::fs::write(path.join(".rrh.toml"), toml).unwrap();
+            }
+            context.db.register(Repository::new(id.into(), path, None), vec![]).unwrap();
+        }
+        let run = |ids: &[&str], args: &[&str]| RunOpts {
+            task: ProjectTask::Test,
+            groups: crate::cli::GroupSpecifier { group_names: vec![] },
+            repositories: crate::cli::RepositorySpecifier { repository_ids: ids.iter().map(|s| s.to_string()).collect() },
+            select: crate::cli::SelectSpecifier { select: None },
+            no_header: true,
+            arguments: args.iter().map(|s| s.to_string()).collect(),
+        };
+        assert!(matches!(perform_run(&mut context, run(&[], &[])), Err(RrhError::Arguments(_))));
+        assert!(perform_run(&mut context, run(&["passing", "bare"], &["arg"])).unwrap());
+        assert!(perform_run(&mut context, run(&["passing"], &[])).is_err());
+        match perform_run(&mut context, run(&["failing", "bare"], &[])) {
+            Err(RrhError::Arrays(errs)) => assert!(matches!(errs[..], [RrhError::ExternalCommand(s, _)] if s.code() == Some(3))),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
 
     #[test]
     fn test_parse_git_url() {
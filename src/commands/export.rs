@@ -0,0 +1,213 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::cli::{ExportOpts, ImportOpts, Result, RrhError};
+use crate::config::Context;
+use crate::db::jsondb::JsonDB;
+use crate::db::{Database, RefDB};
+use crate::selector::Selector;
+
+/// prints (or writes) the database as JSON, including the positions of the relations and the pinned flags.
+/// The paths are collapsed into the roots (e.g., `${HOME}`) unless `--no-replace-home` is given.
+/// With `--select`, only the matched repositories and the groups holding them (with their ancestors) are exported.
+pub(crate) fn perform_export(context: &mut Context, opts: ExportOpts) -> Result<bool> {
+    let mut db = match JsonDB::from_db(context.db.as_ref()) {
+        Ok(db) => db,
+        Err(e) => return Err(e),
+    };
+    if let Some(expr) = opts.select.select.as_deref() {
+        if let Err(e) = retain_selected(context.db.as_ref(), &mut db, expr) {
+            return Err(e);
+        }
+    }
+    if !opts.no_replace_home {
+        db.map_paths(&|p| context.config.collapse_path(p));
+    }
+    let data = match if opts.indent { db.to_pretty_json() } else { db.to_json() } {
+        Ok(data) => data,
+        Err(e) => return Err(e),
+    };
+    if opts.dest == "-" {
+        println!("{}", data);
+        return Ok(false);
+    }
+    let dest = PathBuf::from(&opts.dest);
+    if dest.exists() && !opts.overwrite {
+        return Err(RrhError::Arguments(format!("{}: file exists, use --overwrite", dest.display())));
+    }
+    std::fs::write(dest, data).map(|_| false).map_err(RrhError::IO)
+}
+
+fn retain_selected(source: &dyn RefDB, db: &mut JsonDB, expr: &str) -> Result<()> {
+    let selected = match Selector::parse(expr).and_then(|s| s.select(source)) {
+        Ok(repos) => repos.into_iter().map(|r| r.id).collect::<Vec<_>>(),
+        Err(e) => return Err(e),
+    };
+    for repo in db.repositories().unwrap_or_default() {
+        if !selected.contains(&repo.id) {
+            if let Err(e) = db.delete_repository(repo.id) {
+                return Err(e);
+            }
+        }
+    }
+    let mut kept = vec![];
+    for group in db.groups().unwrap_or_default() {
+        if !db.find_relation_with_group(&group.name).is_empty() {
+            kept.extend(db.find_ancestors(&group.name).into_iter().map(|g| g.name));
+            kept.push(group.name);
+        }
+    }
+    for group in db.groups().unwrap_or_default() {
+        if !kept.contains(&group.name) {
+            if let Err(e) = db.delete_group(group.name) {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// registers the repositories and groups of the exported database, and relates them in the
+/// exported order. The existing ones are kept unless `--overwrite` is given.
+pub(crate) fn perform_import(context: &mut Context, opts: ImportOpts) -> Result<bool> {
+    let mut imported = match read_exported(&opts.src) {
+        Ok(db) => db,
+        Err(e) => return Err(e),
+    };
+    imported.map_paths(&|p| context.config.resolve_path(p));
+    let mut errs = vec![];
+    for group in imported.groups().unwrap_or_default() {
+        let result = match context.db.find_group(&group.name) {
+            Some(_) if opts.overwrite => context.db.update_group(group.name.clone(), group),
+            Some(_) => {
+                log::info!("{}: group already exists, skipped", group.name);
+                Ok(())
+            }
+            None => context.db.register_group(group),
+        };
+        if let Err(e) = result {
+            errs.push(e);
+        }
+    }
+    let mut ids = vec![];
+    for repo in imported.repositories().unwrap_or_default() {
+        let id = repo.id.clone();
+        let result = match context.db.find_repository(&id) {
+            Some(_) if opts.overwrite => context.db.update_repository(id.clone(), repo),
+            Some(_) => {
+                log::info!("{}: repository already exists, skipped", id);
+                continue;
+            }
+            None => context.db.register(repo, vec![]),
+        };
+        match result {
+            Ok(_) => ids.push(id),
+            Err(e) => errs.push(e),
+        }
+    }
+    for group in imported.groups().unwrap_or_default() {
+        let mut position = next_position(context.db.as_ref(), &group.name);
+        for mut relation in imported.find_relation_with_group(&group.name) {
+            if !ids.contains(&relation.id) {
+                continue;
+            }
+            if relation.position.is_some() {
+                relation.position = Some(position);
+                position += 1;
+            }
+            let result = match context.db.relate(relation.id.clone(), relation.group.clone()) {
+                Ok(_) if relation.position.is_some() => context.db.update_relation(relation),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                errs.push(e);
+            }
+        }
+    }
+    if !errs.is_empty() {
+        Err(RrhError::Arrays(errs))
+    } else {
+        Ok(!opts.dry_run)
+    }
+}
+
+/// returns the position following the positions of the existing relations in the group,
+/// so that the imported relations are placed after them.
+fn next_position(db: &dyn RefDB, group_name: &str) -> u32 {
+    db.find_relation_with_group(group_name).iter()
+        .filter_map(|r| r.position)
+        .max()
+        .map(|p| p + 1)
+        .unwrap_or(0)
+}
+
+fn read_exported(src: &str) -> Result<JsonDB> {
+    if src != "-" {
+        return JsonDB::load(PathBuf::from(src));
+    }
+    let mut data = String::new();
+    match std::io::stdin().read_to_string(&mut data) {
+        Ok(_) => JsonDB::from_str(&data),
+        Err(e) => Err(RrhError::IO(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_export_and_import() {
+        let dir = TempDir::new("export");
+        let dest = dir.join("exported.json");
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut repo = context.db.find_repository("helloworld").unwrap();
+        repo.pinned = true;
+        context.db.update_repository(repo.id.clone(), repo).unwrap();
+        let mut relation = context.db.find_relation("helloworld", "no-group").unwrap();
+        relation.position = Some(0);
+        context.db.update_relation(relation).unwrap();
+        let export = |overwrite: bool| ExportOpts {
+            dest: dest.to_string_lossy().to_string(), overwrite, no_replace_home: false, indent: true,
+            select: crate::cli::SelectSpecifier { select: None },
+        };
+        perform_export(&mut context, export(false)).unwrap();
+        assert!(perform_export(&mut context, export(false)).is_err());
+
+        let mut other = Context::new_with_path(dir.bootstrap()).unwrap();
+        let import = ImportOpts { src: dest.to_string_lossy().to_string(), overwrite: false, dry_run: false };
+        assert!(perform_import(&mut other, import).unwrap());
+        assert!(other.db.find_repository("helloworld").unwrap().pinned);
+        let ids = other.db.find_repositories_of("no-group").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld", "fibonacci"]);
+        assert_eq!(other.db.find_relation("helloworld", "no-group").unwrap().position, Some(0));
+
+        let mut merged = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        merged.db.delete_repository("helloworld".into()).unwrap();
+        let mut relation = merged.db.find_relation("fibonacci", "no-group").unwrap();
+        relation.position = Some(3);
+        merged.db.update_relation(relation).unwrap();
+        let import = ImportOpts { src: dest.to_string_lossy().to_string(), overwrite: false, dry_run: false };
+        assert!(perform_import(&mut merged, import).unwrap());
+        let positions = merged.db.find_relation_with_group("no-group").into_iter()
+            .map(|r| (r.id, r.position))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![(String::from("fibonacci"), Some(3)), (String::from("helloworld"), Some(4))]);
+    }
+
+    #[test]
+    fn test_retain_selected() {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut db = JsonDB::from_db(context.db.as_ref()).unwrap();
+        db.register_group(crate::entities::Group::new("unrelated".into())).unwrap();
+        retain_selected(context.db.as_ref(), &mut db, "id:hello*").unwrap();
+        let ids = db.repositories().unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld"]);
+        let names = db.groups().unwrap().into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["no-group"]);
+        assert_eq!(db.find_relation_with_group("no-group").len(), 1);
+    }
+}
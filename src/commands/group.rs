@@ -4,9 +4,11 @@ use tabled::builder::Builder;
 use tabled::settings::Style;
 use tabled::Table;
 
-use crate::config::{Context, EnvValue};
+use crate::config::{keys, Context, EnvValue};
 use crate::{cli::*, utils};
 use crate::entities::Group;
+use crate::selector::Selector;
+use crate::terminal;
 
 pub(crate) fn perform(c: &mut Context, opts: GroupOpts)  -> Result<bool> {
     match opts.subcmd {
@@ -14,17 +16,28 @@ pub(crate) fn perform(c: &mut Context, opts: GroupOpts)  -> Result<bool> {
         GroupSubCommand::List(opts) => perform_list(c, opts),
         GroupSubCommand::Of(opts) => perform_of(c, opts),
         GroupSubCommand::Remove(opts) => perform_remove(c, opts),
+        GroupSubCommand::Reorder(opts) => perform_reorder(c, opts),
         GroupSubCommand::Update(opts) => perform_update(c, opts),
     }
 }
 
 fn perform_add(c: &mut Context, opts: &GroupAddOpts) -> Result<bool> {
     let mut errs = vec![];
+    if let Some(parent) = &opts.parent {
+        if c.db.find_group(parent).is_none() {
+            return Err(RrhError::GroupNotFound(parent.clone()));
+        }
+    }
+    if let Some(Err(e)) = opts.rule.as_ref().map(|r| Selector::parse(r)) {
+        return Err(e);
+    }
     for name in opts.names.clone() {
         if let Some(_) = c.db.find_group(&name) {
             errs.push(RrhError::GroupExists(name.clone()));
         }
-        let group = Group::new_with(name, opts.note.clone().unwrap_or(String::from("")), Some(opts.abbrev));
+        let mut group = Group::new_with(name, opts.note.clone().unwrap_or(String::from("")), Some(opts.abbrev));
+        group.parent = opts.parent.clone();
+        group.rule = opts.rule.clone();
         if let Err(e) = c.db.register_group(group) {
             errs.push(e);
         }
@@ -39,7 +52,7 @@ fn perform_add(c: &mut Context, opts: &GroupAddOpts) -> Result<bool> {
 fn perform_list(c: &Context, opts: GroupListOpts) -> Result<bool> {
     let mut errs = vec![];
     let target = opts.args.clone();
-    let groups = if target.len() > 0 {
+    let mut groups = if target.len() > 0 {
         let mut groups = vec![];
         for name in target {
             if let Some(g) = c.db.find_group(&name) {
@@ -51,10 +64,11 @@ fn perform_list(c: &Context, opts: GroupListOpts) -> Result<bool> {
         groups
     } else {
         match c.db.groups() {
-            Ok(r) => r,
+            Ok(r) => r.into_iter().filter(|g| opts.all || !g.is_hidden()).collect::<Vec<_>>(),
             Err(e) => return Err(e),
         }
     };
+    groups.sort_by_key(|g| g.order.unwrap_or(0));
     let p_opts = &mut opts.p_opts.clone();
     p_opts.update_entries();
     p_opts.update_format(c.config.get_env("print_list_style"));
@@ -69,7 +83,10 @@ fn perform_list(c: &Context, opts: GroupListOpts) -> Result<bool> {
 fn perform_of(c: &Context, opts: GroupOfOpts) -> Result<bool> {
     let mut errs = vec![];
     let mut result = HashMap::<String, Vec<Group>>::new();
-    let target = opts.names.clone();
+    let target = match crate::selector::with_selected_ids(c.db.as_ref(), &opts.names, opts.select.select.as_deref()) {
+        Ok(ids) => ids,
+        Err(e) => return Err(e),
+    };
     for name in target {
         match c.db.find_groups_of(&name) {
             Ok(rs) => {
@@ -89,12 +106,15 @@ fn perform_of(c: &Context, opts: GroupOfOpts) -> Result<bool> {
     }
 }
 
+/// removes the groups, the groups having the repositories or the child groups are removed only by `--force`.
+/// The child groups of the removed groups are moved to their parents.
 pub(crate) fn perform_remove(c: &mut Context, opts: GroupRemoveOpts) -> Result<bool> {
     let mut errs = vec![];
     for name in opts.args.clone() {
         if !opts.force {
             let r = c.db.find_relation_with_group(&name);
-            if r.len() > 0 {
+            let children = c.db.find_children(&name).unwrap_or_default();
+            if !r.is_empty() || !children.is_empty() {
                 errs.push(RrhError::GroupNotEmpty(name.clone()));
                 continue;
             }
@@ -110,11 +130,46 @@ pub(crate) fn perform_remove(c: &mut Context, opts: GroupRemoveOpts) -> Result<b
     }
 }
 
+/// puts the given repositories at the top of the group in the given order, and the others follow
+/// them in their current order.
+fn perform_reorder(c: &mut Context, opts: GroupReorderOpts) -> Result<bool> {
+    if c.db.find_group(&opts.name).is_none() {
+        return Err(RrhError::GroupNotFound(opts.name));
+    }
+    let mut relations = c.db.find_relation_with_group(&opts.name);
+    let errs = opts.repository_ids.iter()
+        .filter(|id| !relations.iter().any(|r| &r.id == *id))
+        .map(|id| RrhError::RelationNotFound(id.clone(), opts.name.clone()))
+        .collect::<Vec<_>>();
+    if !errs.is_empty() {
+        return Err(RrhError::Arrays(errs));
+    }
+    relations.sort_by_key(|r| opts.repository_ids.iter().position(|id| id == &r.id).unwrap_or(usize::MAX));
+    for (position, mut relation) in relations.into_iter().enumerate() {
+        relation.position = Some(position as u32);
+        if let Err(e) = c.db.update_relation(relation) {
+            return Err(e);
+        }
+    }
+    Ok(!opts.dry_run)
+}
+
 pub(crate) fn perform_update(c: &mut Context, opts: GroupUpdateOpts) -> Result<bool> {
     let group = match c.db.find_group(&opts.name) {
         Some(g) => g,
         None => return Err(RrhError::GroupNotFound(opts.name.clone())),
     };
+    if let Some(parent) = opts.parent.as_ref().filter(|p| !p.is_empty()) {
+        if let Err(e) = c.db.check_parent(&opts.name, parent) {
+            return Err(e);
+        }
+    }
+    if let Some(Err(e)) = opts.rule.as_ref().filter(|r| !r.is_empty()).map(|r| Selector::parse(r)) {
+        return Err(e);
+    }
+    if let Some(color) = opts.color.as_ref().filter(|c| !c.is_empty() && !terminal::is_color_name(c)) {
+        return Err(RrhError::CliOptsInvalid("group".into(), format!("{}: unknown color", color)));
+    }
     let new_group = opts.build_new_group(&group);
     if let Err(e) = c.db.update_group(opts.name.clone(), new_group) {
         return Err(e);
@@ -135,6 +190,27 @@ impl GroupUpdateOpts {
         if let Some(abbrev) = &self.abbrev {
             new_group.abbrev = Some(abbrev.clone());
         }
+        if let Some(parent) = &self.parent {
+            new_group.parent = Some(parent.clone()).filter(|p| !p.is_empty());
+        }
+        if let Some(rule) = &self.rule {
+            new_group.rule = Some(rule.clone()).filter(|r| !r.is_empty());
+        }
+        if let Some(dir) = &self.clone_dir {
+            new_group.clone_dir = Some(dir.clone()).filter(|d| !d.is_empty());
+        }
+        if let Some(exec) = &self.exec {
+            new_group.exec = Some(exec.clone()).filter(|e| !e.trim().is_empty());
+        }
+        if let Some(color) = &self.color {
+            new_group.color = Some(color.clone()).filter(|c| !c.is_empty());
+        }
+        if let Some(order) = self.order {
+            new_group.order = Some(order);
+        }
+        if let Some(hidden) = self.hidden {
+            new_group.hidden = Some(hidden);
+        }
         new_group
     }
 }
@@ -159,6 +235,15 @@ fn print_group(c: &Context, groups: Vec<Group>, opts: &GroupPrintingOpts) {
         if opts.is_print_target(&GroupEntry::Abbrev) {
             row.push(group.abbrev.unwrap_or(false).to_string());
         }
+        if opts.is_print_target(&GroupEntry::Parent) {
+            row.push(group.parent.as_ref().map(|p| c.db.group_path(p)).unwrap_or_default());
+        }
+        if opts.is_print_target(&GroupEntry::Rule) {
+            row.push(group.rule.clone().unwrap_or_default());
+        }
+        if opts.is_print_target(&GroupEntry::Settings) {
+            row.push(group.settings().join(" "));
+        }
         if opts.is_print_target(&GroupEntry::Count) {
             let count = match c.db.find_repositories_of(&group.name) {
                 Ok(rs) => rs.len(),
@@ -225,6 +310,15 @@ fn make_table_builder(opts: &GroupPrintingOpts) -> Builder {
         if opts.is_print_target(&GroupEntry::Abbrev) {
             header.push("Abbrev");
         }
+        if opts.is_print_target(&GroupEntry::Parent) {
+            header.push("Parent");
+        }
+        if opts.is_print_target(&GroupEntry::Rule) {
+            header.push("Rule");
+        }
+        if opts.is_print_target(&GroupEntry::Settings) {
+            header.push("Settings");
+        }
         if opts.is_print_target(&GroupEntry::Count) {
             header.push("Count");
         }
@@ -245,10 +339,7 @@ impl GroupPrintingOpts {
     }
 
     fn update_format(&mut self, format: Option<&EnvValue>) {
-        let availables = vec![
-                "psql", "ascii", "ascii_rounded", "empty", "blank", "markdown", "sharp", "rounded", 
-                "modern_rounded", "re_structured_text", "dots", "modern", "extended", "csv",
-        ].iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let availables = keys::LIST_STYLES.iter().map(|s| s.to_string()).collect::<Vec<String>>();
         self.format = if let Some(f) = &self.format {
             let f = f.to_lowercase();
             if availables.contains(&f) {
@@ -287,11 +378,13 @@ mod tests {
             names: vec![String::from("group1"), String::from("group2")],
             note: Some(String::from("note")),
             abbrev: true,
+            parent: None,
+            rule: None,
             dry_run: false,
         };
         let r = perform_add(&mut context, &opts);
         assert!(r.is_ok());
-        assert_eq!(r.unwrap(), true);
+        assert!(r.unwrap());
         assert!(context.db.find_group("group1").is_some());
         assert!(context.db.find_group("group2").is_some());
 
@@ -324,7 +417,7 @@ mod tests {
         };
         let r = perform_remove(&mut context, opts);
         assert!(r.is_ok());
-        assert_eq!(r.unwrap(), true);
+        assert!(r.unwrap());
 
         let r = context.db.groups();
         assert!(r.is_ok());
@@ -339,12 +432,19 @@ mod tests {
             rename_to: Some(String::from("current")),
             note: Some(String::from("note")),
             abbrev: Some(false),
+            parent: None,
+            rule: None,
+            clone_dir: None,
+            exec: None,
+            color: Some(String::from("red")),
+            order: Some(1),
+            hidden: Some(true),
             name: String::from("no-group"),
             dry_run: false,
         };
         let r = perform_update(&mut context, opts);
         assert!(r.is_ok());
-        assert_eq!(r.unwrap(), true);
+        assert!(r.unwrap());
         assert!(context.db.find_group("current").is_some());
 
         let r = context.db.groups();
@@ -353,5 +453,45 @@ mod tests {
 
         let r = context.db.find_relation_with_group("current");
         assert_eq!(r.len(), 2);
+        let g = context.db.find_group("current").unwrap();
+        assert_eq!(g.settings(), vec!["color=red", "order=1", "hidden"]);
+    }
+
+    #[test]
+    fn test_perform_update_parent() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json"))
+                .unwrap();
+        let opts = GroupAddOpts { names: vec![String::from("company")], note: None, abbrev: false, parent: None, rule: None, dry_run: false };
+        perform_add(&mut context, &opts).unwrap();
+        let opts = GroupAddOpts { names: vec![String::from("team")], note: None, abbrev: false, parent: Some(String::from("company")), rule: None, dry_run: false };
+        perform_add(&mut context, &opts).unwrap();
+        let update = |name: &str, parent: &str| GroupUpdateOpts {
+            abbrev: None, note: None, rename_to: None, parent: Some(parent.to_string()), rule: None,
+            clone_dir: None, exec: None, color: None, order: None, hidden: None, name: name.to_string(), dry_run: false,
+        };
+        assert!(matches!(perform_update(&mut context, update("company", "team")), Err(RrhError::GroupCycle(_, _))));
+        perform_update(&mut context, update("no-group", "team")).unwrap();
+        assert_eq!(context.db.group_path("no-group"), "company/team/no-group");
+        assert_eq!(context.db.find_repositories_under("company").unwrap().len(), 2);
+
+        perform_update(&mut context, update("no-group", "")).unwrap();
+        assert_eq!(context.db.find_group("no-group").unwrap().parent, None);
+    }
+
+    #[test]
+    fn test_perform_reorder() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json"))
+                .unwrap();
+        let reorder = |ids: Vec<&str>| GroupReorderOpts {
+            name: String::from("no-group"),
+            repository_ids: ids.iter().map(|id| id.to_string()).collect(),
+            dry_run: false,
+        };
+        perform_reorder(&mut context, reorder(vec!["helloworld"])).unwrap();
+        let ids = context.db.find_repositories_of("no-group").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld", "fibonacci"]);
+        let positions = context.db.find_relation_with_group("no-group").into_iter().map(|r| r.position).collect::<Vec<_>>();
+        assert_eq!(positions, vec![Some(0), Some(1)]);
+        assert!(matches!(perform_reorder(&mut context, reorder(vec!["unknown"])), Err(RrhError::Arrays(_))));
     }
 }
\ No newline at end of file
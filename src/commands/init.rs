@@ -22,6 +22,12 @@ pub fn perform(_: &Context, c: InitOpts) -> Result<bool> {
     if !c.without_rrhpeco {
         print_asset("rrhpeco", shell.clone());
     }
+    if !c.without_rrhpick {
+        print_asset("rrhpick", shell.clone());
+    }
+    if !c.without_completion {
+        print_asset("_rrh", shell.clone());
+    }
     let _ = std::io::stdout().flush();
     Ok(false)
 }
@@ -31,7 +37,7 @@ fn print_asset(name: &str, shell: String) {
     if let Some(asset) = Asset::get(&asset_path) {
         print_asset_impl(asset);
     } else {
-        eprintln!("Asset not found: {}", asset_path);
+        log::error!("{}: asset not found", asset_path);
     }
 }
 
@@ -47,4 +53,23 @@ fn shell_to_string(shell: ShellName) -> String {
         ShellName::Elvish => "elvish".to_string(),
         ShellName::Powershell => "powershell".to_string(),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+
+    use super::*;
+
+    #[test]
+    fn test_assets_for_all_shells() {
+        let required = ["cdrrh", "_cdrrh", "rrhfzf", "rrhpeco", "rrhpick", "_rrh"];
+        for shell in ShellName::value_variants() {
+            let shell = shell_to_string(shell.clone());
+            for name in required.iter() {
+                let asset_path = format!("assets/{}/{}", shell, name);
+                assert!(Asset::get(&asset_path).is_some(), "{}: asset not found", asset_path);
+            }
+        }
+    }
+}
@@ -4,17 +4,26 @@ use itertools::Itertools;
 use tabled::Table;
 use tabled::{builder::Builder, settings::Style};
 
-use crate::cli::{RepositoryEntry, RepositoryListOpts, Result, RrhError, RepositoryPrintingOpts};
-use crate::config::{self, Config, Context, EnvValue};
+use crate::cli::{FindOpts, RepositoryEntry, RepositoryListOpts, Result, RrhError, RepositoryPrintingOpts};
+use crate::config::{self, keys, Config, Context, EnvValue};
+use crate::db::RefDB;
 use crate::entities::{Repository, RepositoryWithGroups};
-use crate::terminal::to_string_in_columns;
+use crate::selector::Selector;
+use crate::terminal::{colorize, to_string_in_columns};
 use crate::utils::format_humanize;
 
+use super::pin::pinned_first;
 use super::RecentOpts;
 
 pub(crate) fn perform_recent(context: &Context, mut c: RecentOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&[], c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
     let result = context.db.repositories().unwrap().iter()
+        .filter(|r| selector.as_ref().is_none_or(|s| s.matches(r, context.db.as_ref())))
         .sorted_by(|&a, &b| a.last_access.cmp(&b.last_access))
+        .sorted_by_key(|r| !r.pinned)
         .take(c.number.unwrap_or(5))
         .map(|r| build_repo_with_group(r, context))
         .collect::<Vec<_>>();
@@ -25,9 +34,7 @@ pub(crate) fn perform_recent(context: &Context, mut c: RecentOpts) -> Result<boo
 }
 
 fn build_repo_with_group(r: &Repository, context: &Context) -> RepositoryWithGroups {
-    let groups = context.db.find_relation_with_repository(&r.id).iter()
-        .map(|r| context.db.find_group(&r.group).unwrap())
-        .collect::<Vec<_>>();
+    let groups = context.db.find_groups_of(&r.id).unwrap_or_default();
     RepositoryWithGroups {
         repo: r.clone(),
         groups,
@@ -35,33 +42,89 @@ fn build_repo_with_group(r: &Repository, context: &Context) -> RepositoryWithGro
 }
 
 pub(crate) fn perform_list(context: &Context, mut c: RepositoryListOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&c.tags.tags, c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
     let mut errs = Vec::<RrhError>::new();
     let mut result = HashMap::<String, Vec<Repository>>::new();
     if c.groups.len() == 0 {
         match context.db.group_repositories() {
-            Ok(rs) => result = rs,
+            Ok(rs) => result = rs.into_iter()
+                .filter(|(g, _)| c.all || !context.db.find_group(g).is_some_and(|g| g.is_hidden()))
+                .collect(),
             Err(e) => _ = errs.push(e),
         }
     } else {
         for group in c.groups {
-            match context.db.find_repositories_of(&group) {
-                Ok(rs) => {
-                    result.insert(group, rs);
-                }
+            let mut names = vec![group.clone()];
+            match context.db.find_descendants(&group) {
+                Ok(groups) => names.extend(groups.into_iter()
+                    .filter(|g| c.all || !g.is_hidden())
+                    .map(|g| g.name)),
                 Err(e) => errs.push(e),
             }
+            for name in names {
+                match context.db.find_repositories_of(&name) {
+                    Ok(rs) => {
+                        result.insert(name, rs);
+                    }
+                    Err(e) => errs.push(e),
+                }
+            }
         }
     }
     if errs.len() != 0 {
         return Err(RrhError::Arrays(errs));
     }
+    if let Some(s) = selector {
+        result = result.into_iter()
+            .map(|(g, repos)| (g, repos.into_iter().filter(|r| s.matches(r, context.db.as_ref())).collect::<Vec<_>>()))
+            .filter(|(_, repos)| !repos.is_empty())
+            .collect();
+    }
+    result.values_mut().for_each(|repos| pinned_first(repos, |r| r));
     let p_opts = &mut c.p_opts;
     p_opts.update_entries();
     p_opts.update_format(context.config.get_env("print_list_style"));
     print_result(result, p_opts, context)
 }
 
+/// prints the repositories matched with the keywords (any of them, or all of them by `--and`)
+/// in the ids, paths, descriptions, tags, and project metadata, and matched with the selection.
+pub(crate) fn perform_find(context: &Context, mut c: FindOpts) -> Result<bool> {
+    let result = match find_repositories(context, &c) {
+        Ok(result) => result,
+        Err(e) => return Err(e),
+    };
+    let p_opts = &mut c.p_opts;
+    p_opts.update_entries();
+    p_opts.update_format(context.config.get_env("print_list_style"));
+    print_table_repo_group(result, p_opts, &context.config)
+}
+
+fn find_repositories(context: &Context, c: &FindOpts) -> Result<Vec<RepositoryWithGroups>> {
+    let selector = match Selector::from_options(&c.tags.tags, c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let repos = match context.db.repositories() {
+        Ok(repos) => repos,
+        Err(e) => return Err(e),
+    };
+    Ok(repos.iter()
+        .filter(|r| selector.as_ref().is_none_or(|s| s.matches(r, context.db.as_ref())))
+        .filter(|r| match c.and {
+            _ if c.keywords.is_empty() => true,
+            true => c.keywords.iter().all(|k| r.matches(k)),
+            false => c.keywords.iter().any(|k| r.matches(k)),
+        })
+        .map(|r| build_repo_with_group(r, context))
+        .collect())
+}
+
 pub(crate) fn print_list(repos: Vec<RepositoryWithGroups>, config: &mut Config, p_opts: &mut RepositoryPrintingOpts) -> Result<bool> {
+    p_opts.update_entries();
     p_opts.update_format(config.get_env("print_list_style"));
     print_table_repo_group(repos, p_opts, config)
 }
@@ -78,45 +141,100 @@ fn print_result(
             &context.config,
         )
     } else {
-        for (group_name, repos) in result.iter() {
+        let order = tree_order(context.db.as_ref(), result.keys().cloned().collect());
+        // the group headers are omitted by `--no-header`, since its output is piped into the other tools.
+        let with_headers = !opts.no_headers && order.iter().any(|(name, depth)| {
+            *depth > 0 || context.db.find_group(name).is_some_and(|g| g.color.is_some())
+        });
+        let colored = std::io::stdout().is_terminal();
+        for (group_name, depth) in order {
+            let repos = &result[&group_name];
             let group = match context.db.find_group(&group_name) {
                 Some(g) => g,
                 None => break,
             };
-            if group.is_abbrev() && result.len() > 1 {
-                print_abbrev(repos, opts, group_name, &context.config)
+            let table = if group.is_abbrev() && result.len() > 1 {
+                abbrev_table(repos, opts, &group_name, &context.config)
             } else {
-                print_table(repos, opts, group_name, &context.config)
+                group_table(repos, opts, &group_name, &context.config)
+            };
+            if !with_headers {
+                println!("{}", table.to_string());
+                continue;
+            }
+            let indent = "  ".repeat(depth);
+            let header = format!("{}/", group_name);
+            match group.color.as_ref().filter(|_| colored) {
+                Some(color) => println!("{}{}", indent, colorize(&header, color)),
+                None => println!("{}{}", indent, header),
+            }
+            if !repos.is_empty() {
+                for line in table.to_string().lines() {
+                    println!("{}  {}", indent, line);
+                }
             }
         }
         Ok(true)
     }
 }
 
+/// orders the group names by the depth-first order of the group tree with their depths.
+/// The depths count only the ancestors in the given names, and the siblings are sorted by
+/// their sort orders and names.
+fn tree_order(db: &dyn RefDB, mut names: Vec<String>) -> Vec<(String, usize)> {
+    names.sort();
+    names.sort_by_key(|name| db.find_group(name).and_then(|g| g.order).unwrap_or(0));
+    let parents = names.iter()
+        .map(|name| {
+            let parent = db.find_ancestors(name).into_iter()
+                .map(|g| g.name)
+                .find(|a| names.contains(a));
+            (name.clone(), parent)
+        })
+        .collect::<Vec<_>>();
+    let mut result: Vec<(String, usize)> = vec![];
+    let mut stack = parents.iter()
+        .filter(|(_, parent)| parent.is_none())
+        .map(|(name, _)| (name.clone(), 0))
+        .rev()
+        .collect::<Vec<_>>();
+    while let Some((name, depth)) = stack.pop() {
+        stack.extend(parents.iter()
+            .filter(|(_, parent)| parent.as_ref() == Some(&name))
+            .map(|(child, _)| (child.clone(), depth + 1))
+            .rev());
+        result.push((name, depth));
+    }
+    result
+}
+
 fn is_print_target(entries: &Vec<RepositoryEntry>, e: RepositoryEntry) -> bool {
     entries.iter().any(|x| *x == e)
 }
 
+/// the columns of the repository table in the printing order.
+const COLUMNS: [(RepositoryEntry, &str); 11] = [
+    (RepositoryEntry::Id, "ID"),
+    (RepositoryEntry::Groups, "Groups"),
+    (RepositoryEntry::Tags, "Tags"),
+    (RepositoryEntry::Path, "Path"),
+    (RepositoryEntry::Description, "Description"),
+    (RepositoryEntry::LastAccess, "Last Access"),
+    (RepositoryEntry::Language, "Language"),
+    (RepositoryEntry::Build, "Build"),
+    (RepositoryEntry::Test, "Test"),
+    (RepositoryEntry::Docs, "Docs"),
+    (RepositoryEntry::Owners, "Owners"),
+];
+
+fn columns_of(entries: &Vec<RepositoryEntry>) -> impl Iterator<Item = &(RepositoryEntry, &'static str)> + '_ {
+    COLUMNS.iter().filter(|(e, _)| is_print_target(entries, e.clone()))
+}
+
 fn build_table_builder(entries: &Vec<RepositoryEntry>, no_header: bool) -> Builder {
     let mut builder = Builder::new();
     if !no_header {
-        let mut header = vec![];
-        if is_print_target(&entries, RepositoryEntry::Id) {
-            header.push(String::from("ID"));
-        }
-        if is_print_target(&entries, RepositoryEntry::Groups) {
-            header.push(String::from("Groups"));
-        }
-        if is_print_target(&entries, RepositoryEntry::Path) {
-            header.push(String::from("Path"));
-        }
-        if is_print_target(&entries, RepositoryEntry::Description) {
-            header.push(String::from("Description"))
-        }
-        if is_print_target(&entries, RepositoryEntry::LastAccess) {
-            header.push(String::from("Last Access"));
-        }
-        builder.push_record(header);
+        builder.push_record(columns_of(entries).map(|(_, header)| header.to_string()));
     }
     builder
 }
@@ -131,27 +249,25 @@ pub(crate) fn print_table_repo_group(repos: Vec<RepositoryWithGroups>, opts: &Re
     Ok(false)
 }
 
-fn print_abbrev(result: &Vec<Repository>, opts: &RepositoryPrintingOpts, group_name: &str, _config: &config::Config) {
+fn abbrev_table(result: &Vec<Repository>, opts: &RepositoryPrintingOpts, group_name: &str, _config: &config::Config) -> Table {
     let mut builder = Builder::new();
     let record = vec![String::from("Group"), group_name.to_string(), format!("{}", format_humanize(result.len(), "repository", "repositories"))];
     builder.push_record(record);
-    let table = apply_style(builder, &opts.format);
-    println!("{}", table.to_string());
+    apply_style(builder, &opts.format)
 }
 
-fn print_table(
+fn group_table(
     result: &Vec<Repository>,
     opts: &RepositoryPrintingOpts,
     g: &str,
     config: &config::Config
-) {
+) -> Table {
     let mut builder = build_table_builder(&opts.entries, opts.no_headers);
     result
         .iter()
         .map(|r| map_to_vec(&opts.entries, r, g, config))
         .for_each(|v| builder.push_record(v));
-    let table = apply_style(builder, &opts.format);
-    println!("{}", table.to_string());
+    apply_style(builder, &opts.format)
 }
 
 fn apply_style(builder: Builder, s: &Option<String>) -> Table {
@@ -197,23 +313,8 @@ fn apply_style(builder: Builder, s: &Option<String>) -> Table {
 }
 
 fn map_to_vec_repo_group(entries: &Vec<RepositoryEntry>, r: &RepositoryWithGroups, c: &config::Config) -> Vec<String> {
-    let mut result = vec![];
-    if is_print_target(entries, RepositoryEntry::Id) {
-        result.push(r.repo.id.clone());
-    }
-    if is_print_target(entries, RepositoryEntry::Groups) {
-        result.push(r.groups.iter().map(|g| g.name.clone()).join(", "));
-    }
-    if is_print_target(entries, RepositoryEntry::Path) {
-        result.push(r.repo.path.to_string_lossy().to_string());
-    }
-    if is_print_target(entries, RepositoryEntry::Description) {
-        result.push(r.repo.description.clone().unwrap_or(String::from("")));
-    }
-    if is_print_target(entries, RepositoryEntry::LastAccess) {
-        result.push(r.repo.last_access_string(c));
-    }
-    result
+    let groups = r.groups.iter().map(|g| g.name.clone()).join(", ");
+    map_to_vec(entries, &r.repo, &groups, c)
 }
 
 fn map_to_vec(
@@ -222,23 +323,12 @@ fn map_to_vec(
     g: &str,
     c: &config::Config,
 ) -> Vec<String> {
-    let mut result = vec![];
-    if is_print_target(entries, RepositoryEntry::Id) {
-        result.push(r.id.clone());
-    }
-    if is_print_target(entries, RepositoryEntry::Groups) {
-        result.push(g.to_string());
-    }
-    if is_print_target(entries, RepositoryEntry::Path) {
-        result.push(r.path.to_string_lossy().to_string());
-    }
-    if is_print_target(entries, RepositoryEntry::Description) {
-        result.push(r.description.clone().unwrap_or(String::from("")));
-    }
-    if is_print_target(entries, RepositoryEntry::LastAccess) {
-        result.push(r.last_access_string(c));
-    }
-    result
+    columns_of(entries)
+        .map(|(e, _)| match e {
+            RepositoryEntry::Groups => g.to_string(),
+            e => e.clone().to_string(r, c),
+        })
+        .collect()
 }
 
 fn print_items_in_columns(
@@ -267,23 +357,14 @@ fn print_items_in_columns(
 impl RepositoryPrintingOpts {
     fn update_entries(&mut self) {
         if self.entries.contains(&RepositoryEntry::All) {
-            self.entries = vec![
-                RepositoryEntry::Id,
-                RepositoryEntry::Groups,
-                RepositoryEntry::Path,
-                RepositoryEntry::Description,
-                RepositoryEntry::LastAccess,
-            ]
+            self.entries = COLUMNS.iter().map(|(e, _)| e.clone()).collect()
         } else if self.entries.len() == 0 {
             self.entries = vec![RepositoryEntry::Id]
         }
     }
 
     fn update_format(&mut self, format: Option<&EnvValue>) {
-        let availables = vec![
-                "psql", "ascii", "ascii_rounded", "empty", "blank", "markdown", "sharp", "rounded", 
-                "modern_rounded", "re_structured_text", "dots", "modern", "extended", "csv",
-        ].iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let availables = keys::LIST_STYLES.iter().map(|s| s.to_string()).collect::<Vec<String>>();
         self.format = if let Some(f) = &self.format {
             let f = f.to_lowercase();
             if availables.contains(&f) {
@@ -305,4 +386,46 @@ impl RepositoryPrintingOpts {
             }
         };
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use super::*;
+
+    fn find_ids(context: &Context, args: &[&str]) -> Vec<String> {
+        let opts = FindOpts::try_parse_from(std::iter::once("find").chain(args.iter().copied())).unwrap();
+        find_repositories(context, &opts).unwrap().into_iter().map(|r| r.repo.id).collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        assert_eq!(find_ids(&context, &["fib", "hello"]), vec!["fibonacci", "helloworld"]);
+        assert!(find_ids(&context, &["--and", "fib", "hello"]).is_empty());
+        assert_eq!(find_ids(&context, &["--and", "hello", "WORLD"]), vec!["helloworld"]);
+        assert_eq!(find_ids(&context, &["o", "--select", "not id:hello*"]), vec!["fibonacci"]);
+        assert!(find_ids(&context, &["unknown"]).is_empty());
+
+        let opts = FindOpts::try_parse_from(["find", "fib"]).unwrap();
+        assert!(!perform_find(&context, opts).unwrap());
+    }
+
+    #[test]
+    fn test_list_with_broken_rule() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut broken = crate::entities::Group::new("broken".into());
+        broken.rule = Some(String::from("(group:work"));
+        context.db.register_group(broken).unwrap();
+        context.db.relate("fibonacci".into(), "broken".into()).unwrap();
+
+        assert!(perform_list(&context, RepositoryListOpts::try_parse_from(["list"]).unwrap()).is_ok());
+        assert!(perform_list(&context, RepositoryListOpts::try_parse_from(["list", "broken"]).unwrap()).is_ok());
+        assert!(perform_list(&context, RepositoryListOpts::try_parse_from(["list", "--select", "group:broken"]).unwrap()).is_ok());
+        let ids = context.db.find_repositories_of("broken").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["fibonacci"]);
+    }
+}
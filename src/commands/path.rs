@@ -0,0 +1,126 @@
+use crate::cli::{PathOpts, Result, RrhError};
+use crate::config::Context;
+use crate::db::RefDB;
+use crate::entities::Repository;
+use crate::selector::Selector;
+
+pub(crate) fn perform_path(context: &Context, c: PathOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&[], c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    // the query picked from the table of `list` (e.g., by fzf) has the padding spaces.
+    match resolve(context.db.as_ref(), c.query.trim(), selector.as_ref()) {
+        Ok(r) => {
+            println!("{}", r.path.to_string_lossy());
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// find the only one repository matched with the given query.
+/// The query is examined in the following order, and the first matched rule wins.
+///   1. exact repository id,
+///   2. `GROUP/QUERY` (the rest of rules are applied to the repositories in GROUP),
+///   3. prefix of the repository id,
+///   4. fuzzy (subsequence) match of the repository id.
+///
+/// The candidates are narrowed by the selector (`--select`) if given.
+pub(crate) fn resolve(db: &dyn RefDB, query: &str, selector: Option<&Selector>) -> Result<Repository> {
+    let selected = |r: &Repository| selector.is_none_or(|s| s.matches(r, db));
+    if let Some(r) = db.find_repository(query).filter(|r| selected(r)) {
+        return Ok(r);
+    }
+    let (candidates, q) = match query.split_once('/') {
+        Some((group, q)) if db.find_group(group).is_some() => {
+            match db.find_repositories_of(group) {
+                Ok(rs) => (rs, q),
+                Err(e) => return Err(e),
+            }
+        }
+        _ => match db.repositories() {
+            Ok(rs) => (rs, query),
+            Err(e) => return Err(e),
+        },
+    };
+    let candidates = candidates.into_iter().filter(|r| selected(r)).collect();
+    let matched = find_matched(candidates, q);
+    match matched.len() {
+        0 => Err(RrhError::RepositoryNotFound(query.to_string())),
+        1 => Ok(matched[0].clone()),
+        _ => Err(RrhError::AmbiguousRepository(
+            query.to_string(),
+            matched.iter().map(|r| r.id.clone()).collect(),
+        )),
+    }
+}
+
+fn find_matched(candidates: Vec<Repository>, query: &str) -> Vec<Repository> {
+    let exact = candidates.iter()
+        .filter(|r| r.id == query)
+        .cloned()
+        .collect::<Vec<_>>();
+    if !exact.is_empty() {
+        return exact;
+    }
+    let prefixed = candidates.iter()
+        .filter(|r| r.id.starts_with(query))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !prefixed.is_empty() {
+        return prefixed;
+    }
+    candidates.into_iter()
+        .filter(|r| is_fuzzy_match(&r.id, query))
+        .collect()
+}
+
+/// returns true if all characters in the query appear in the target in the same order.
+pub(crate) fn is_fuzzy_match(target: &str, query: &str) -> bool {
+    let target = target.to_lowercase();
+    let mut chars = target.chars();
+    query.to_lowercase()
+        .chars()
+        .all(|q| chars.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn context() -> Context {
+        Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let c = context();
+        assert_eq!(resolve(c.db.as_ref(), "fibonacci", None).unwrap().id, "fibonacci");
+        assert_eq!(resolve(c.db.as_ref(), "hello", None).unwrap().id, "helloworld");
+        assert_eq!(resolve(c.db.as_ref(), "no-group/fib", None).unwrap().id, "fibonacci");
+        assert_eq!(resolve(c.db.as_ref(), "hwld", None).unwrap().id, "helloworld");
+    }
+
+    #[test]
+    fn test_resolve_failure() {
+        let c = context();
+        match resolve(c.db.as_ref(), "o", None) {
+            Err(RrhError::AmbiguousRepository(_, candidates)) => assert_eq!(candidates.len(), 2),
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert!(matches!(resolve(c.db.as_ref(), "unknown", None), Err(RrhError::RepositoryNotFound(_))));
+        let selector = Selector::parse("not id:fib*").unwrap();
+        assert_eq!(resolve(c.db.as_ref(), "o", Some(&selector)).unwrap().id, "helloworld");
+        assert!(resolve(c.db.as_ref(), "fibonacci", Some(&selector)).is_err());
+    }
+
+    #[test]
+    fn test_is_fuzzy_match() {
+        assert!(is_fuzzy_match("helloworld", "hwd"));
+        assert!(is_fuzzy_match("helloworld", "HeLLo"));
+        assert!(!is_fuzzy_match("helloworld", "dlrow"));
+    }
+}
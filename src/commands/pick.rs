@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{clear, cursor, style};
+
+use crate::cli::{PickOpts, PickOutput, Result, RrhError};
+use crate::commands::path::is_fuzzy_match;
+use crate::commands::pin::pinned_first;
+use crate::config::Context;
+use crate::entities::RepositoryWithGroups;
+use crate::selector::Selector;
+use crate::utils;
+
+pub(crate) fn perform_pick(context: &Context, c: PickOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&[], c.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let candidates = match find_candidates(context, &c.groups, selector.as_ref()) {
+        Ok(rs) => rs,
+        Err(e) => return Err(e),
+    };
+    if candidates.is_empty() {
+        return Err(RrhError::Arguments(String::from(
+            "(pick) any repositories are not found",
+        )));
+    }
+    let mut picker = Picker::new(candidates, c.query.clone().unwrap_or_default(), c.multi);
+    match picker.run() {
+        Ok(chosen) => {
+            print_chosen(&c, chosen);
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn find_candidates(context: &Context, groups: &Vec<String>, selector: Option<&Selector>) -> Result<Vec<RepositoryWithGroups>> {
+    let repos = if groups.is_empty() {
+        match context.db.repositories() {
+            Ok(rs) => rs,
+            Err(e) => return Err(e),
+        }
+    } else {
+        let mut repos = vec![];
+        let mut errs = vec![];
+        for name in groups {
+            if context.db.find_group(name).is_none() {
+                errs.push(RrhError::GroupNotFound(name.clone()));
+                continue;
+            }
+            match context.db.find_repositories_under(name) {
+                Ok(rs) => repos.extend(rs),
+                Err(e) => errs.push(e),
+            }
+        }
+        if !errs.is_empty() {
+            return Err(RrhError::Arrays(errs));
+        }
+        repos
+    };
+    let mut result: Vec<RepositoryWithGroups> = vec![];
+    for r in repos {
+        if result.iter().any(|item| item.repo.id == r.id)
+            || selector.is_some_and(|s| !s.matches(&r, context.db.as_ref())) {
+            continue;
+        }
+        if let Some(item) = context.db.find_repository_with_groups(&r.id) {
+            result.push(item);
+        }
+    }
+    pinned_first(&mut result, |item| &item.repo);
+    Ok(result)
+}
+
+fn print_chosen(c: &PickOpts, chosen: Vec<RepositoryWithGroups>) {
+    for item in chosen {
+        if c.cd {
+            println!("cd {}", shell_quote(&item.repo.path.to_string_lossy()));
+        } else {
+            match c.output {
+                PickOutput::Id => println!("{}", item.repo.id),
+                PickOutput::Path => println!("{}", item.repo.path.to_string_lossy()),
+            }
+        }
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Accept,
+    Cancel,
+}
+
+/// in-process fuzzy finder drawn on the controlling terminal (/dev/tty),
+/// so that the standard output is kept for the chosen results.
+pub(crate) struct Picker {
+    items: Vec<RepositoryWithGroups>,
+    query: String,
+    multi: bool,
+    filtered: Vec<usize>,
+    selected: Vec<usize>,
+    cursor: usize,
+    offset: usize,
+    statuses: HashMap<usize, Vec<String>>,
+}
+
+impl Picker {
+    pub(crate) fn new(items: Vec<RepositoryWithGroups>, query: String, multi: bool) -> Self {
+        let mut picker = Self {
+            items,
+            query,
+            multi,
+            filtered: vec![],
+            selected: vec![],
+            cursor: 0,
+            offset: 0,
+            statuses: HashMap::new(),
+        };
+        picker.update_filter();
+        picker
+    }
+
+    /// shows the finder and returns the chosen items. The empty vector means the user cancelled.
+    pub(crate) fn run(&mut self) -> Result<Vec<RepositoryWithGroups>> {
+        let tty = termion::get_tty().map_err(RrhError::IO)?;
+        let input = tty.try_clone().map_err(RrhError::IO)?;
+        let mut screen = tty
+            .into_raw_mode()
+            .and_then(|t| t.into_alternate_screen())
+            .map_err(RrhError::IO)?;
+        let mut result = vec![];
+        self.render(&mut screen, termion::terminal_size_fd(&input).unwrap_or((80, 24)))
+            .map_err(RrhError::IO)?;
+        for key in input.try_clone().map_err(RrhError::IO)?.keys() {
+            match self.handle_key(key.map_err(RrhError::IO)?) {
+                Action::Accept => {
+                    result = self.chosen();
+                    break;
+                }
+                Action::Cancel => break,
+                Action::Continue => {}
+            }
+            self.render(&mut screen, termion::terminal_size_fd(&input).unwrap_or((80, 24)))
+                .map_err(RrhError::IO)?;
+        }
+        let _ = write!(screen, "{}", cursor::Show);
+        let _ = screen.flush();
+        Ok(result)
+    }
+
+    fn update_filter(&mut self) {
+        let query = self.query.clone();
+        self.filtered = self.items.iter().enumerate()
+            .filter(|(_, item)| is_fuzzy_match(&search_text(item), &query))
+            .map(|(i, _)| i)
+            .collect();
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    fn handle_key(&mut self, key: Key) -> Action {
+        match key {
+            Key::Char('\n') => return Action::Accept,
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('g') => return Action::Cancel,
+            Key::Char('\t') => {
+                self.toggle_selection();
+                self.move_cursor(1);
+            }
+            Key::Up | Key::Ctrl('p') | Key::Ctrl('k') => self.move_cursor(-1),
+            Key::Down | Key::Ctrl('n') | Key::Ctrl('j') => self.move_cursor(1),
+            Key::Backspace | Key::Ctrl('h') if self.query.pop().is_some() => self.update_filter(),
+            Key::Ctrl('u') => {
+                self.query.clear();
+                self.update_filter();
+            }
+            Key::Char(c) => {
+                self.query.push(c);
+                self.update_filter();
+            }
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        if self.filtered.is_empty() {
+            return;
+        }
+        let max = self.filtered.len() as isize - 1;
+        self.cursor = (self.cursor as isize + delta).clamp(0, max) as usize;
+    }
+
+    fn toggle_selection(&mut self) {
+        if !self.multi {
+            return;
+        }
+        if let Some(&index) = self.filtered.get(self.cursor) {
+            match self.selected.iter().position(|&i| i == index) {
+                Some(p) => _ = self.selected.remove(p),
+                None => self.selected.push(index),
+            }
+        }
+    }
+
+    fn chosen(&self) -> Vec<RepositoryWithGroups> {
+        if !self.selected.is_empty() {
+            self.selected.iter().map(|&i| self.items[i].clone()).collect()
+        } else {
+            self.filtered.get(self.cursor)
+                .map(|&i| vec![self.items[i].clone()])
+                .unwrap_or_default()
+        }
+    }
+
+    fn status_of(&mut self, index: usize) -> Vec<String> {
+        let path = self.items[index].repo.path.clone();
+        self.statuses.entry(index)
+            .or_insert_with(|| match utils::git_status(&path) {
+                Ok(lines) => lines,
+                Err(e) => vec![format!("(git status unavailable: {:?})", e)],
+            })
+            .clone()
+    }
+
+    fn render(&mut self, out: &mut impl Write, (cols, rows): (u16, u16)) -> std::io::Result<()> {
+        let cols = cols as usize;
+        let rows = rows.max(6) as usize;
+        let list_height = (rows - 2) / 2;
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + list_height {
+            self.offset = self.cursor + 1 - list_height;
+        }
+        write!(out, "{}{}{}", cursor::Hide, clear::All, cursor::Goto(1, 1))?;
+        let counts = format!("{}/{}", self.filtered.len(), self.items.len());
+        write!(out, "> {}  {}", self.query, counts)?;
+        for (line, &index) in self.filtered.iter().enumerate().skip(self.offset).take(list_height) {
+            let item = &self.items[index];
+            let mark = if self.selected.contains(&index) { "*" } else { " " };
+            let groups = item.groups.iter().map(|g| g.name.clone()).collect::<Vec<_>>().join(", ");
+            let text = truncate(&format!("{} {}  [{}]", mark, item.repo.id, groups), cols);
+            write!(out, "{}", cursor::Goto(1, (line - self.offset + 2) as u16))?;
+            if line == self.cursor {
+                write!(out, "{}{}{}", style::Invert, text, style::Reset)?;
+            } else {
+                write!(out, "{}", text)?;
+            }
+        }
+        let preview_top = list_height + 2;
+        write!(out, "{}{}", cursor::Goto(1, preview_top as u16), "-".repeat(cols))?;
+        for (i, line) in self.preview().iter().take(rows - preview_top).enumerate() {
+            write!(out, "{}{}", cursor::Goto(1, (preview_top + i + 1) as u16), truncate(line, cols))?;
+        }
+        write!(out, "{}", cursor::Goto((self.query.chars().count() + 3) as u16, 1))?;
+        out.flush()
+    }
+
+    fn preview(&mut self) -> Vec<String> {
+        let index = match self.filtered.get(self.cursor) {
+            Some(&i) => i,
+            None => return vec![],
+        };
+        let item = &self.items[index];
+        let mut lines = vec![
+            format!("ID:          {}", item.repo.id),
+            format!("Path:        {}", item.repo.path.to_string_lossy()),
+            format!("Description: {}", item.repo.description.clone().unwrap_or_default()),
+            format!("Groups:      {}", item.groups.iter().map(|g| g.name.clone()).collect::<Vec<_>>().join(", ")),
+            String::new(),
+        ];
+        lines.extend(self.status_of(index));
+        lines
+    }
+}
+
+fn search_text(item: &RepositoryWithGroups) -> String {
+    let mut text = item.repo.id.clone();
+    for g in item.groups.iter() {
+        text.push(' ');
+        text.push_str(&g.name);
+    }
+    text
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn picker(multi: bool) -> Picker {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let items = find_candidates(&context, &vec![], None).unwrap();
+        Picker::new(items, String::new(), multi)
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut p = picker(false);
+        assert_eq!(p.filtered.len(), 2);
+        p.handle_key(Key::Char('h'));
+        p.handle_key(Key::Char('w'));
+        assert_eq!(p.filtered.len(), 1);
+        assert_eq!(p.chosen()[0].repo.id, "helloworld");
+        p.handle_key(Key::Backspace);
+        p.handle_key(Key::Backspace);
+        assert_eq!(p.filtered.len(), 2);
+        assert_eq!(p.handle_key(Key::Char('\n')), Action::Accept);
+    }
+
+    #[test]
+    fn test_multi_select() {
+        let mut p = picker(true);
+        p.handle_key(Key::Char('\t'));
+        p.handle_key(Key::Char('\t'));
+        let chosen = p.chosen().iter().map(|r| r.repo.id.clone()).collect::<Vec<_>>();
+        assert_eq!(chosen, vec!["fibonacci", "helloworld"]);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/tmp/it's"), "'/tmp/it'\\''s'");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::cli::{PinOpts, Result, RrhError};
+use crate::config::Context;
+use crate::entities::Repository;
+use crate::selector;
+
+/// pins (or unpins) the repositories, the pinned ones are shown first in `list`, `recent` and `pick`.
+pub(crate) fn perform_pin(context: &mut Context, opts: PinOpts, pinned: bool) -> Result<bool> {
+    let ids = match selector::with_selected_ids(context.db.as_ref(), &opts.repository_ids, opts.select.select.as_deref()) {
+        Ok(ids) => ids,
+        Err(e) => return Err(e),
+    };
+    let mut errs = vec![];
+    for id in ids.iter() {
+        let mut repo = match context.db.find_repository(id) {
+            Some(r) => r,
+            None => {
+                errs.push(RrhError::RepositoryNotFound(id.clone()));
+                continue;
+            }
+        };
+        if repo.pinned == pinned {
+            log::info!("{}: already {}", id, if pinned { "pinned" } else { "unpinned" });
+            continue;
+        }
+        repo.pinned = pinned;
+        if let Err(e) = context.db.update_repository(id.clone(), repo) {
+            errs.push(e);
+        }
+    }
+    if !errs.is_empty() {
+        Err(RrhError::Arrays(errs))
+    } else {
+        Ok(!opts.dry_run)
+    }
+}
+
+/// moves the pinned repositories to the front, keeping the order of the others.
+pub(crate) fn pinned_first<T, F>(items: &mut [T], repo: F)
+        where F: Fn(&T) -> &Repository {
+    items.sort_by_key(|item| !repo(item).pinned);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::cli::SelectSpecifier;
+
+    use super::*;
+
+    fn pin_opts(ids: Vec<&str>) -> PinOpts {
+        PinOpts {
+            repository_ids: ids.iter().map(|id| id.to_string()).collect(),
+            select: SelectSpecifier { select: None },
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        assert!(perform_pin(&mut context, pin_opts(vec!["fibonacci"]), true).unwrap());
+        assert!(context.db.find_repository("fibonacci").unwrap().pinned);
+        assert!(perform_pin(&mut context, pin_opts(vec!["fibonacci", "unknown"]), false).is_err());
+        assert!(!context.db.find_repository("fibonacci").unwrap().pinned);
+        let opts = PinOpts { select: SelectSpecifier { select: Some(String::from("group:no-group")) }, ..pin_opts(vec![]) };
+        perform_pin(&mut context, opts, true).unwrap();
+        assert!(context.db.repositories().unwrap().iter().all(|r| r.pinned));
+
+        let mut repos = vec![
+            Repository::new("a".into(), PathBuf::from("a"), None),
+            Repository::new("b".into(), PathBuf::from("b"), None),
+            Repository::new("c".into(), PathBuf::from("c"), None),
+        ];
+        repos[2].pinned = true;
+        pinned_first(&mut repos, |r| r);
+        assert_eq!(repos.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["c", "a", "b"]);
+    }
+}
@@ -0,0 +1,31 @@
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+use crate::cli::{PluginsOpts, Result};
+use crate::config::Context;
+use crate::external::{self, Description};
+
+pub(crate) fn perform(context: &Context, opts: PluginsOpts) -> Result<bool> {
+    let mut builder = Builder::new();
+    builder.push_record(vec!["Name", "Version", "Description", "Path"]);
+    for plugin in external::find_plugins() {
+        let description = if opts.no_describe {
+            Description::default()
+        } else {
+            plugin.describe(context).unwrap_or_default()
+        };
+        if let Some(api) = description.api.as_ref().filter(|api| *api != external::PLUGIN_API) {
+            log::warn!("{}: plugin api {} differs from {}", plugin.name, api, external::PLUGIN_API);
+        }
+        builder.push_record(vec![
+            plugin.name,
+            description.version.unwrap_or_default(),
+            description.description,
+            plugin.path.display().to_string(),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    println!("{}", table);
+    Ok(false)
+}
@@ -0,0 +1,155 @@
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+use crate::cli::{ProfileAddOpts, ProfileOpts, ProfileRemoveOpts, ProfileSubCommand, ProfileUseOpts, Result, RrhError};
+use crate::config::profile::{self, DEFAULT_PROFILE};
+use crate::config::{self, Config};
+
+pub(crate) fn perform(selected: Option<String>, opts: ProfileOpts) -> Result<bool> {
+    match opts.subcmd {
+        ProfileSubCommand::List => perform_list(&profile::selected(selected)),
+        ProfileSubCommand::Add(opts) => perform_add(opts),
+        ProfileSubCommand::Use(opts) => perform_use(opts),
+        ProfileSubCommand::Remove(opts) => perform_remove(opts),
+    }
+}
+
+fn perform_list(current: &str) -> Result<bool> {
+    let mut builder = Builder::new();
+    builder.push_record(vec!["", "Profile", "Database", "Stack"]);
+    let mut names = vec![String::from(DEFAULT_PROFILE)];
+    names.extend(profile::names());
+    for name in names {
+        let mark = if name == current { "*" } else { "" };
+        let (database, stack) = match Config::new_with_path(profile::config_path_of(&name)) {
+            Ok(c) => (c.database_path.display().to_string(), c.stack.join(",")),
+            Err(_) => (String::from("(not created)"), String::new()),
+        };
+        builder.push_record(vec![mark.to_string(), name, database, stack]);
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    println!("{}", table);
+    Ok(false)
+}
+
+fn perform_add(opts: ProfileAddOpts) -> Result<bool> {
+    if let Err(e) = profile::validate_name(&opts.name) {
+        return Err(e);
+    }
+    if profile::exists(&opts.name) {
+        return Err(RrhError::ProfileExists(opts.name));
+    }
+    let errs = opts.stack.iter()
+        .filter(|name| **name == opts.name || !profile::exists(name))
+        .map(|name| RrhError::ProfileNotFound(name.clone()))
+        .collect::<Vec<_>>();
+    if !errs.is_empty() {
+        return Err(RrhError::Arrays(errs));
+    }
+    let path = profile::profiles_dir().join(&opts.name).join("config.json");
+    let mut config = Config::default_at(path);
+    if let Some(database) = opts.database {
+        config.database_path = database;
+    }
+    config.stack = opts.stack;
+    if let Err(e) = config::bootstrap(&config) {
+        return Err(e);
+    }
+    if opts.use_it {
+        if let Err(e) = profile::set_current(&opts.name) {
+            return Err(e);
+        }
+    }
+    Ok(false)
+}
+
+fn perform_use(opts: ProfileUseOpts) -> Result<bool> {
+    if profile::exists(&opts.name) {
+        profile::set_current(&opts.name).map(|_| false)
+    } else {
+        Err(RrhError::ProfileNotFound(opts.name))
+    }
+}
+
+fn perform_remove(opts: ProfileRemoveOpts) -> Result<bool> {
+    if opts.name == DEFAULT_PROFILE {
+        return Err(RrhError::CliOptsInvalid(
+            "profile".into(),
+            format!("{}: the default profile cannot be removed", opts.name),
+        ));
+    }
+    if !profile::exists(&opts.name) {
+        return Err(RrhError::ProfileNotFound(opts.name));
+    }
+    let path = profile::config_path_of(&opts.name);
+    if opts.with_database {
+        match Config::new_with_path(path.clone()) {
+            Ok(c) if c.database_path.exists() => {
+                if let Err(e) = std::fs::remove_file(&c.database_path) {
+                    return Err(RrhError::IO(e));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if let Err(e) = std::fs::remove_file(&path) {
+        return Err(RrhError::IO(e));
+    }
+    let _ = std::fs::remove_dir(profile::profiles_dir().join(&opts.name));
+    for name in profile::names() {
+        if let Ok(c) = Config::new_with_path(profile::config_path_of(&name)) {
+            if c.stack.contains(&opts.name) {
+                log::warn!("{}: the removed profile is stacked under {}", opts.name, name);
+            }
+        }
+    }
+    if profile::current().as_deref() == Some(opts.name.as_str()) {
+        profile::set_current(DEFAULT_PROFILE).map(|_| false)
+    } else {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Context, EnvGuard, TempDir};
+    use crate::entities::Repository;
+
+    use super::*;
+
+    fn add_opts(name: &str, stack: Vec<&str>) -> ProfileAddOpts {
+        ProfileAddOpts {
+            database: None,
+            stack: stack.iter().map(|s| s.to_string()).collect(),
+            use_it: false,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_profiles() {
+        let dir = TempDir::new("profile");
+        let _env = EnvGuard::set("config_dir", &*dir);
+
+        assert!(perform_add(add_opts("personal", vec!["team"])).is_err());
+        perform_add(add_opts("team", vec![])).unwrap();
+        assert!(matches!(perform_add(add_opts("team", vec![])), Err(RrhError::ProfileExists(_))));
+        perform_add(add_opts("personal", vec!["team"])).unwrap();
+        assert_eq!(profile::names(), vec!["personal", "team"]);
+
+        let mut team = Context::new_with_profile("team").unwrap();
+        team.db.register(Repository::new("shared".into(), "shared".into(), None), vec![]).unwrap();
+        team.store().unwrap();
+
+        perform_use(ProfileUseOpts { name: String::from("personal") }).unwrap();
+        assert_eq!(profile::current(), Some(String::from("personal")));
+        let personal = Context::new_with_profile("personal").unwrap();
+        assert!(personal.db.find_repository("shared").is_some());
+
+        perform_remove(ProfileRemoveOpts { with_database: true, name: String::from("personal") }).unwrap();
+        assert_eq!(profile::current(), None);
+        assert!(matches!(perform_use(ProfileUseOpts { name: String::from("personal") }), Err(RrhError::ProfileNotFound(_))));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::cli::{ProjectOpts, ProjectSetOpts, ProjectSubCommand, ProjectSyncOpts, Result, RrhError};
+use crate::config::Context;
+use crate::selector::{self, Selector};
+
+pub(crate) fn perform(context: &mut Context, opts: ProjectOpts) -> Result<bool> {
+    match opts.subcmd {
+        ProjectSubCommand::Set(opts) => perform_set(context, opts),
+        ProjectSubCommand::Sync(opts) => perform_sync(context, opts),
+    }
+}
+
+fn perform_set(context: &mut Context, opts: ProjectSetOpts) -> Result<bool> {
+    let mut repo = match context.db.find_repository(&opts.repository_id) {
+        Some(r) => r,
+        None => return Err(RrhError::RepositoryNotFound(opts.repository_id)),
+    };
+    let project = &mut repo.project;
+    for (field, value) in [
+        (&mut project.language, opts.language),
+        (&mut project.build, opts.build),
+        (&mut project.test, opts.test),
+        (&mut project.docs, opts.docs),
+    ] {
+        if let Some(value) = value {
+            *field = Some(value).filter(|v| !v.is_empty());
+        }
+    }
+    if let Some(owners) = opts.owners {
+        project.owners = owners.into_iter().filter(|o| !o.is_empty()).collect();
+    }
+    context.db.update_repository(opts.repository_id, repo).map(|_| true)
+}
+
+fn perform_sync(context: &mut Context, opts: ProjectSyncOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&[], opts.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let no_targets = opts.ids.is_empty();
+    let repos = if no_targets {
+        match context.db.repositories() {
+            Ok(repos) => repos,
+            Err(e) => return Err(e),
+        }
+    } else {
+        let mut repos = vec![];
+        for id in opts.ids {
+            match context.db.find_repository(&id) {
+                Some(r) => repos.push(r),
+                None => return Err(RrhError::RepositoryNotFound(id)),
+            }
+        }
+        repos
+    };
+    let repos = match selector::narrow(context.db.as_ref(), repos, no_targets, selector.as_ref()) {
+        Ok(repos) => repos,
+        Err(e) => return Err(e),
+    };
+    let mut updated = false;
+    let mut errs = vec![];
+    for mut repo in repos {
+        match repo.refresh_project() {
+            Ok(true) => {
+                log::info!("{}: the project metadata was updated", repo.id);
+                match context.db.update_repository(repo.id.clone(), repo) {
+                    Ok(_) => updated = true,
+                    Err(e) => errs.push(e),
+                }
+            }
+            Ok(false) => {}
+            Err(e) => errs.push(e),
+        }
+    }
+    if errs.is_empty() {
+        Ok(updated && !opts.dry_run)
+    } else {
+        Err(RrhError::Arrays(errs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::config::TempDir;
+    use crate::entities::Repository;
+
+    use super::*;
+
+    #[test]
+    fn test_set_and_sync() {
+        let dir = TempDir::new("project");
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        context.db.register(Repository::new("project".into(), dir.to_path_buf(), None), vec![]).unwrap();
+
+        let opts = ProjectSetOpts {
+            language: Some("rust".into()),
+            build: None,
+            test: Some("cargo test".into()),
+            docs: None,
+            owners: Some(vec!["alice".into(), "bob".into()]),
+            repository_id: "project".into(),
+        };
+        assert!(perform_set(&mut context, opts).unwrap());
+        let repo = context.db.find_repository("project").unwrap();
+        assert_eq!(repo.project.test, Some(String::from("cargo test")));
+        assert!(repo.matches("BOB"));
+
+        std::fs::write(dir.join(".rrh.toml"), "test = \"make test\"\ndocs = \"https://example.com\"\n").unwrap();
+        let opts = ProjectSyncOpts { ids: vec![], select: crate::cli::SelectSpecifier { select: Some("id:project".into()) }, dry_run: false };
+        assert!(perform_sync(&mut context, opts).unwrap());
+        let project = context.db.find_repository("project").unwrap().project;
+        assert_eq!(project.test, Some(String::from("make test")));
+        assert_eq!(project.language, Some(String::from("rust")));
+        assert_eq!(project.docs, Some(String::from("https://example.com")));
+    }
+}
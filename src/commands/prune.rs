@@ -1,9 +1,12 @@
+use std::io::IsTerminal;
+
 use inquire::InquireError;
 
-use crate::cli::{GroupUpdateOpts, PruneOpts, RemoveOpts, RenameOpts, RepositoryUpdateOpts, Result, RrhError};
+use crate::cli::{GroupUpdateOpts, PruneOpts, RemoveOpts, RenameOpts, RepositoryUpdateOpts, Result, RrhError, SelectSpecifier};
 use crate::config::Context;
 use crate::db::Database;
 use crate::entities::{Group, Repository};
+use crate::selector::Selector;
 
 use crate::commands::{group, repository};
 
@@ -71,6 +74,13 @@ fn rename_repository(c: &mut Context, old_name: &str, new_name: &str, dry_run: b
 fn rename_group(c: &mut Context, old_name: &str, new_name: &str, dry_run: bool) -> Result<bool> {
     let g_opts = GroupUpdateOpts {
         abbrev: None,
+        parent: None,
+        rule: None,
+        clone_dir: None,
+        exec: None,
+        color: None,
+        order: None,
+        hidden: None,
         note: None,
         rename_to: Some(new_name.to_string()),
         name: old_name.to_string(),
@@ -79,8 +89,29 @@ fn rename_group(c: &mut Context, old_name: &str, new_name: &str, dry_run: bool)
     group::perform_update(c, g_opts)
 }
 
+/// removes the given repositories or groups, and the repositories matched with `--select`.
+/// It asks the confirmation if `--select` matches more than one repository (or in the inquiry mode),
+/// unless `--yes` is given.
 pub(crate) fn perform_remove(c: &mut Context, opts: RemoveOpts) -> Result<bool> {
     use RepoOrGroup::*;
+    let selected = match Selector::from_options(&[], opts.select.select.as_deref()) {
+        Ok(Some(s)) => s.select(c.db.as_ref()),
+        Ok(None) => Ok(vec![]),
+        Err(e) => Err(e),
+    };
+    let selected = match selected {
+        Ok(repos) => repos,
+        Err(e) => return Err(e),
+    };
+    if !opts.yes && (selected.len() > 1 || opts.inquiry) {
+        let mut names = opts.targets.clone();
+        names.extend(selected.iter().map(|r| r.id.clone()).filter(|id| !opts.targets.contains(id)));
+        match confirm_removal(&names) {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
     let mut errs = vec![];
     for name in opts.targets.clone() {
         let e = match find_repo_or_group(c, &name) {
@@ -92,6 +123,14 @@ pub(crate) fn perform_remove(c: &mut Context, opts: RemoveOpts) -> Result<bool>
             errs.push(e);
         }
     }
+    for repo in selected {
+        if c.db.find_repository(&repo.id).is_none() {
+            continue;
+        }
+        if let Err(e) = remove_repository(c, repo.id, &opts) {
+            errs.push(e);
+        }
+    }
     if errs.len() > 0 {
         Err(RrhError::Arrays(errs))
     } else {
@@ -99,9 +138,37 @@ pub(crate) fn perform_remove(c: &mut Context, opts: RemoveOpts) -> Result<bool>
     }
 }
 
+/// lists the targets and asks whether to remove them, the confirmation needs the terminal.
+pub(crate) fn confirm_removal(names: &[String]) -> Result<bool> {
+    if names.is_empty() {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(RrhError::CliOptsInvalid(
+            "remove".into(),
+            format!("{}: confirm the removal with --yes", names.join(", ")),
+        ));
+    }
+    for name in names {
+        eprintln!("  {}", name);
+    }
+    match inquire::prompt_confirmation(build_removal_message(names)) {
+        Ok(b) => Ok(b),
+        Err(InquireError::IO(e)) => Err(RrhError::IO(e)),
+        Err(e) => Err(RrhError::Fatal(e.to_string())),
+    }
+}
+
+fn build_removal_message(names: &[String]) -> String {
+    use crate::utils::format_humanize;
+    format!("Do you want to remove {}?", format_humanize(names.len(), "target", "targets"))
+}
+
 fn remove_repository(c: &mut Context, name: String, opts: &RemoveOpts) -> Result<bool> {
     let new_opts = RepositoryRemoveOpts {
         ids: vec![name],
+        select: SelectSpecifier { select: None },
+        yes: true,
         dry_run: opts.dry_run,
     };
     repository::perform_remove(c, new_opts)
@@ -139,8 +206,10 @@ fn find_empty_groups(db: &Box<dyn Database>) -> Vec<String> {
         .collect::<Vec<_>>()
 }
 
+/// the smart groups are not empty since their members change by their rules.
 fn is_empty_group(db: &Box<dyn Database>, group_name: &str) -> bool {
-    db.find_repositories_of(group_name).unwrap().len() == 0
+    !db.find_group(group_name).is_some_and(|g| g.is_smart())
+        && db.find_repositories_under(group_name).unwrap().len() == 0
 }
 
 fn prune_impl(c: &mut Context, target_groups: Vec<String>, target_repos: Vec<Repository>) -> Result<bool> {
@@ -190,6 +259,35 @@ mod tests {
         assert_eq!(c.db.groups().unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_remove_with_auto_delete_group() {
+        let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let opts = crate::cli::RemoveOpts { inquiry: false, force: false, yes: false, dry_run: false, select: crate::cli::SelectSpecifier { select: None }, targets: vec![String::from("fibonacci")] };
+        assert!(perform_remove(&mut c, opts).is_ok());
+        assert!(c.db.find_group("no-group").is_some());
+
+        let opts = crate::cli::RemoveOpts { inquiry: false, force: false, yes: false, dry_run: false, select: crate::cli::SelectSpecifier { select: None }, targets: vec![String::from("helloworld")] };
+        assert!(perform_remove(&mut c, opts).is_ok());
+        assert!(c.db.find_group("no-group").is_none());
+    }
+
+    #[test]
+    fn test_remove_with_select() {
+        let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let select = crate::cli::SelectSpecifier { select: Some(String::from("id~^hello")) };
+        let opts = crate::cli::RemoveOpts { inquiry: false, force: false, yes: false, dry_run: false, select, targets: vec![] };
+        assert!(perform_remove(&mut c, opts).is_ok());
+        assert!(c.db.find_repository("helloworld").is_none());
+        assert!(c.db.find_repository("fibonacci").is_some());
+
+        let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let select = crate::cli::SelectSpecifier { select: Some(String::from("group:no-group")) };
+        let opts = crate::cli::RemoveOpts { inquiry: false, force: false, yes: true, dry_run: false, select, targets: vec![] };
+        assert!(perform_remove(&mut c, opts).is_ok());
+        assert!(c.db.repositories().unwrap().is_empty());
+        assert_eq!(build_removal_message(&[String::from("fibonacci"), String::from("helloworld")]), "Do you want to remove 2 targets?");
+    }
+
     #[test]
     fn test_rename_repo() {
         let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
@@ -1,9 +1,10 @@
 use crate::config::Context;
 use crate::cli::{Result, RepositorySubCommand, RepositoryOpts};
-use crate::commands::{list, add};
+use crate::commands::{list, add, prune};
 
 use crate::cli::{RepositoryEntry, RepositoryInfoOpts, RepositoryRemoveOpts, RepositoryUpdateOpts};
 use crate::entities::{Group, Repository, RepositoryWithGroups};
+use crate::selector;
 
 use super::RrhError;
 
@@ -18,7 +19,11 @@ pub fn perform(c: &mut Context, opts: RepositoryOpts) -> Result<bool> {
 }
 
 fn perform_info(c: &mut Context, opts: RepositoryInfoOpts) -> Result<bool> {
-    let repos = opts.ids.iter()
+    let ids = match selector::with_selected_ids(c.db.as_ref(), &opts.ids, opts.select.select.as_deref()) {
+        Ok(ids) => ids,
+        Err(e) => return Err(e),
+    };
+    let repos = ids.iter()
             .map(|id| c.db.find_repository_with_groups(&id))
             .filter(|r| r.is_some())
             .map(|r| r.unwrap())
@@ -33,14 +38,32 @@ fn perform_info(c: &mut Context, opts: RepositoryInfoOpts) -> Result<bool> {
     Ok(false)
 }
 
+/// removes the given repositories and the repositories matched with `--select`.
+/// It asks the confirmation if `--select` matches more than one repository, unless `--yes` is given.
 pub(crate) fn perform_remove(c: &mut Context, opts: RepositoryRemoveOpts) -> Result<bool> {
-    if opts.ids.len() > 0 {
+    let ids = match selector::with_selected_ids(c.db.as_ref(), &opts.ids, opts.select.select.as_deref()) {
+        Ok(ids) => ids,
+        Err(e) => return Err(e),
+    };
+    if !opts.yes && ids.len() > opts.ids.len() + 1 {
+        match prune::confirm_removal(&ids) {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+    if !ids.is_empty() {
         let mut errs = vec![];
-        for id in opts.ids {
+        let mut groups = vec![];
+        for id in ids {
+            groups.extend(c.db.find_relation_with_repository(&id).into_iter().map(|r| r.group));
             if let Err(e) = c.db.delete_repository(id) {
                 errs.push(e)
             }
         }
+        if let Err(e) = delete_empty_groups(c, groups) {
+            errs.push(e)
+        }
         if errs.len() > 0 {
             return Err(RrhError::Arrays(errs))
         } else {
@@ -62,7 +85,10 @@ pub(crate) fn perform_update(c: &mut Context, opts: RepositoryUpdateOpts) -> Res
             }
             match repository_update_impl(c, opts.repository_id, new_repo, g) {
                 Err(e) => Err(e),
-                Ok(f) => Ok(!opts.dry_run || f),
+                Ok(f) => match delete_empty_groups(c, r.groups.into_iter().map(|g| g.name).collect()) {
+                    Err(e) => Err(e),
+                    Ok(_) => Ok(!opts.dry_run || f),
+                },
             }
         },
         None => Err(RrhError::RepositoryNotFound(opts.repository_id.clone()))
@@ -103,15 +129,10 @@ fn repository_update_impl(c: &mut Context, old_id: String, r: Repository, g: Vec
     }
 }
 
-fn relate_with(c: &mut Context, group_name: &str, repo_id: &str) -> Result<()> {
+pub(crate) fn relate_with(c: &mut Context, group_name: &str, repo_id: &str) -> Result<()> {
     if !c.db.has_relation(repo_id, group_name) {
-        if c.db.find_group(group_name) == None {
-            if let Err(e)  = match c.config.is_env_value_true("auto_create_group") {
-                Some(true) => c.db.register_group(Group::new(group_name.to_string())),
-                _ => Err(RrhError::GroupNotFound(group_name.to_string()))
-            } {
-                return Err(e)
-            }
+        if let Err(e) = ensure_group(c, group_name) {
+            return Err(e)
         }
         if let Err(e) = c.db.relate(repo_id.to_string(), group_name.to_string()) {
             return Err(e)
@@ -120,6 +141,46 @@ fn relate_with(c: &mut Context, group_name: &str, repo_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// registers the group if it does not exist and `auto_create_group` is set.
+/// The default group is always registered.
+pub(crate) fn ensure_group(c: &mut Context, group_name: &str) -> Result<()> {
+    if c.db.find_group(group_name).is_some() {
+        return Ok(())
+    }
+    let is_default = c.config.value_or_default("default_group")
+        .map(|v| v.to_string() == group_name)
+        .unwrap_or(false);
+    match c.config.is_env_value_true("auto_create_group") {
+        Some(true) => c.db.register_group(Group::new(group_name.to_string())),
+        _ if is_default => c.db.register_group(Group::new(group_name.to_string())),
+        _ => Err(RrhError::GroupNotFound(group_name.to_string()))
+    }
+}
+
+/// deletes the given groups which have no repositories if `auto_delete_group` is set.
+/// The groups having the child groups, the note, the rule, or the settings are kept.
+pub(crate) fn delete_empty_groups(c: &mut Context, group_names: Vec<String>) -> Result<()> {
+    if c.config.is_env_value_true("auto_delete_group") != Some(true) {
+        return Ok(())
+    }
+    let mut errs = vec![];
+    for name in group_names {
+        if c.db.find_group(&name).is_some_and(|g| g.is_bare())
+                && c.db.find_relation_with_group(&name).is_empty()
+                && c.db.find_children(&name).is_ok_and(|children| children.is_empty()) {
+            match c.db.delete_group(name) {
+                Ok(_) | Err(RrhError::ReadOnlyEntry(_, _)) => {}
+                Err(e) => errs.push(e),
+            }
+        }
+    }
+    if errs.len() > 0 {
+        Err(RrhError::Arrays(errs))
+    } else {
+        Ok(())
+    }
+}
+
 fn build_new_repo(c: &Context, r: RepositoryWithGroups, opts: &RepositoryUpdateOpts) -> (Repository, Vec<String>) {
     let mut new_repo = opts.build_new_repo(&r.repo);
     let _ = new_repo.last_access(&c.config);
@@ -153,4 +214,36 @@ impl RepositoryUpdateOpts {
         }
         new_repo
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_delete_empty_groups() {
+        let mut c = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        c.db.register_group(Group::new("company".into())).unwrap();
+        let mut team = Group::new("team".into());
+        team.parent = Some(String::from("company"));
+        c.db.register_group(team).unwrap();
+        c.db.register_group(Group::new_with("noted".into(), "keep me".into(), None)).unwrap();
+        let mut smart = Group::new("smart".into());
+        smart.rule = Some(String::from("dirty"));
+        c.db.register_group(smart).unwrap();
+        let mut colored = Group::new("colored".into());
+        colored.color = Some(String::from("red"));
+        c.db.register_group(colored).unwrap();
+        c.db.register_group(Group::new("empty".into())).unwrap();
+        let names = ["company", "team", "noted", "smart", "colored", "empty"];
+        delete_empty_groups(&mut c, names.iter().map(|n| n.to_string()).collect()).unwrap();
+        assert!(c.db.find_group("company").is_some());
+        assert!(c.db.find_group("team").is_none());
+        assert!(c.db.find_group("noted").is_some());
+        assert!(c.db.find_group("smart").is_some());
+        assert!(c.db.find_group("colored").is_some());
+        assert!(c.db.find_group("empty").is_none());
+    }
+}
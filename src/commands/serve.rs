@@ -0,0 +1,455 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::cli::{AddOpts, GroupSpecifier, RepositoryOption, RepositoryRemoveOpts, Result, RrhError, SelectSpecifier, ServeOpts};
+use crate::commands::{add, repository};
+use crate::config::{self, Context};
+use crate::entities::{Repository, RepositorySummary};
+use crate::selector::Selector;
+use crate::utils;
+
+/// The protocol of `rrh serve` is JSON-RPC 2.0, one request (or response) per line.
+///   query:    list {group?, select?}, find {keyword}, groups, info {id}, status {id}
+///   mutation: add {path, id?, groups?, description?}, relate {id, group}, remove {id}
+/// Each connection keeps the loaded database, and reloads it when the files are updated.
+/// At most `MAX_CONNECTIONS` connections are served at once, the others wait until one is closed.
+/// The mutations reload the database and store it while holding the lock as the command line does.
+pub(crate) fn perform(config_file: Option<PathBuf>, profile: Option<String>, opts: ServeOpts) -> Result<bool> {
+    let loader = Loader { config_file, profile };
+    if let Err(e) = loader.load() {
+        return Err(e);
+    }
+    match (opts.socket, opts.port) {
+        (Some(path), _) => serve_unix(loader, &path),
+        (None, Some(port)) => serve_tcp(loader, port),
+        (None, None) => Err(RrhError::CliOptsInvalid("serve".into(), "--socket or --port is required".into())),
+    }
+}
+
+const MAX_CONNECTIONS: usize = 8;
+
+fn serve_tcp(loader: Loader, port: u16) -> Result<bool> {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(l) => l,
+        Err(e) => return Err(RrhError::IO(e)),
+    };
+    if let Ok(addr) = listener.local_addr() {
+        println!("listening on {}", addr);
+    }
+    let limiter = Limiter::default();
+    loop {
+        let permit = limiter.acquire();
+        match listener.accept().and_then(|(s, _)| s.try_clone().map(|w| (s, w))) {
+            Ok((reader, writer)) => spawn(loader.clone(), reader, writer, permit),
+            Err(e) => log::warn!("accept: {}", e),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_unix(loader: Loader, path: &Path) -> Result<bool> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(RrhError::CliOptsInvalid("serve".into(), format!("{}: already in use", path.display())));
+        }
+        if let Err(e) = std::fs::remove_file(path) {
+            return Err(RrhError::IO(e));
+        }
+    }
+    let listener = match UnixListener::bind(path) {
+        Ok(l) => l,
+        Err(e) => return Err(RrhError::IO(e)),
+    };
+    println!("listening on {}", path.display());
+    let limiter = Limiter::default();
+    loop {
+        let permit = limiter.acquire();
+        match listener.accept().and_then(|(s, _)| s.try_clone().map(|w| (s, w))) {
+            Ok((reader, writer)) => spawn(loader.clone(), reader, writer, permit),
+            Err(e) => log::warn!("accept: {}", e),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_loader: Loader, path: &Path) -> Result<bool> {
+    Err(RrhError::CliOptsInvalid("serve".into(), format!("{}: unix domain socket is not supported", path.display())))
+}
+
+fn spawn<R, W>(loader: Loader, reader: R, writer: W, permit: Permit)
+        where R: std::io::Read + Send + 'static, W: Write + Send + 'static {
+    std::thread::spawn(move || {
+        let _permit = permit;
+        let mut session = Session::new(loader);
+        if let Err(e) = session.serve(BufReader::new(reader), writer) {
+            log::warn!("connection closed: {}", e);
+        }
+    });
+}
+
+/// counts the connections in service, and blocks the acceptance while `MAX_CONNECTIONS` are served.
+#[derive(Clone, Default)]
+struct Limiter(Arc<(Mutex<usize>, Condvar)>);
+
+/// the slot of a connection, released on drop.
+struct Permit(Limiter);
+
+impl Limiter {
+    fn acquire(&self) -> Permit {
+        let (count, released) = &*self.0;
+        let mut count = count.lock().unwrap_or_else(|e| e.into_inner());
+        while *count >= MAX_CONNECTIONS {
+            count = released.wait(count).unwrap_or_else(|e| e.into_inner());
+        }
+        *count += 1;
+        Permit(self.clone())
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let (count, released) = &*(self.0).0;
+        *count.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        released.notify_one();
+    }
+}
+
+/// loads the context in the same manner as the command line (`--config-file` and `--profile`).
+#[derive(Debug, Clone)]
+struct Loader {
+    config_file: Option<PathBuf>,
+    profile: Option<String>,
+}
+
+impl Loader {
+    fn load(&self) -> Result<Context> {
+        crate::load_context(self.config_file.clone(), self.profile.clone())
+    }
+}
+
+/// the fingerprints of the database and the config.
+type Stamp = (Option<u64>, Option<u64>);
+
+struct Session {
+    loader: Loader,
+    context: Option<Context>,
+    stamp: Stamp,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: String) -> Self {
+        Self { code, message }
+    }
+}
+
+impl From<RrhError> for RpcError {
+    fn from(e: RrhError) -> Self {
+        RpcError::new(-32000, e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    group: Option<String>,
+    select: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FindParams {
+    keyword: String,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct AddParams {
+    path: PathBuf,
+    id: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RelateParams {
+    id: String,
+    group: String,
+}
+
+impl Session {
+    fn new(loader: Loader) -> Self {
+        Self { loader, context: None, stamp: (None, None) }
+    }
+
+    fn serve<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// returns the response of the given request, or `None` for the notification (no id).
+    fn handle(&mut self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(-32700, e.to_string()))),
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(|m| m.as_str()) {
+            Some(m) => m.to_string(),
+            None => return Some(error_response(id.unwrap_or(Value::Null), RpcError::new(-32600, "method is required".into()))),
+        };
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        log::info!("serve: {} {}", method, params);
+        let result = self.call(&method, params);
+        id.map(|id| match result {
+            Ok(value) => json!({"jsonrpc": "2.0", "id": id, "result": value}),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "list" => {
+                let p: ListParams = parse(params)?;
+                let context = self.context()?;
+                let selector = Selector::from_options(&[], p.select.as_deref())?;
+                let repos = match p.group {
+                    Some(group) => context.db.find_repositories_of(&group),
+                    None => context.db.repositories(),
+                }?.into_iter()
+                    .filter(|r| selector.as_ref().is_none_or(|s| s.matches(r, context.db.as_ref())))
+                    .collect();
+                Ok(summaries(context, repos))
+            }
+            "find" => {
+                let p: FindParams = parse(params)?;
+                let context = self.context()?;
+                let repos = context.db.repositories()?.into_iter()
+                    .filter(|r| r.matches(&p.keyword))
+                    .collect();
+                Ok(summaries(context, repos))
+            }
+            "groups" => {
+                let context = self.context()?;
+                let mut groups = vec![];
+                for g in context.db.groups()? {
+                    let ids = context.db.find_repositories_of(&g.name)?.into_iter()
+                        .map(|r| r.id)
+                        .collect::<Vec<_>>();
+                    groups.push(json!({"name": g.name, "note": g.note, "abbrev": g.is_abbrev(), "parent": g.parent, "rule": g.rule, "repositories": ids}));
+                }
+                Ok(Value::Array(groups))
+            }
+            "info" => {
+                let p: IdParams = parse(params)?;
+                let context = self.context()?;
+                match context.db.find_repository(&p.id) {
+                    Some(r) => Ok(json!({
+                        "repository": RepositorySummary::new(r.clone(), context.db.as_ref()),
+                        "last_access": r.last_access_string(&context.config),
+                    })),
+                    None => Err(RrhError::RepositoryNotFound(p.id).into()),
+                }
+            }
+            "status" => {
+                let p: IdParams = parse(params)?;
+                let context = self.context()?;
+                match context.db.find_repository(&p.id) {
+                    Some(r) => Ok(json!({"id": r.id, "status": utils::git_status(&r.path)?})),
+                    None => Err(RrhError::RepositoryNotFound(p.id).into()),
+                }
+            }
+            "add" => {
+                let p: AddParams = parse(params)?;
+                let opts = AddOpts {
+                    repo: RepositoryOption {
+                        repository_id: p.id,
+                        groups: GroupSpecifier { group_names: p.groups },
+                        description: p.description,
+                    },
+                    paths: vec![p.path],
+                    dry_run: false,
+                };
+                self.update(|c| add::perform_add(c, opts))
+            }
+            "relate" => {
+                let p: RelateParams = parse(params)?;
+                self.update(|c| match c.db.find_repository(&p.id) {
+                    Some(_) => repository::relate_with(c, &p.group, &p.id).map(|_| true),
+                    None => Err(RrhError::RepositoryNotFound(p.id.clone())),
+                })
+            }
+            "remove" => {
+                let p: IdParams = parse(params)?;
+                self.update(|c| match c.db.find_repository(&p.id) {
+                    Some(_) => repository::perform_remove(c, RepositoryRemoveOpts { ids: vec![p.id.clone()], select: SelectSpecifier { select: None }, yes: true, dry_run: false }),
+                    None => Err(RrhError::RepositoryNotFound(p.id.clone())),
+                })
+            }
+            _ => Err(RpcError::new(-32601, format!("{}: method not found", method))),
+        }
+    }
+
+    /// returns the loaded context, and reloads it if the config or the database was updated.
+    /// The cached members of the smart groups are cleared for each request.
+    fn context(&mut self) -> Result<&Context> {
+        let reload = match &self.context {
+            Some(c) => stamp_of(c) != self.stamp,
+            None => true,
+        };
+        if reload {
+            match self.loader.load() {
+                Ok(c) => {
+                    self.stamp = stamp_of(&c);
+                    self.context = Some(c);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        // the rules (e.g., `dirty`) refer the working trees, which may change between the requests.
+        if let Some(cache) = self.context.as_ref().and_then(|c| c.db.rule_cache()) {
+            cache.clear();
+        }
+        self.context.as_ref().ok_or(RrhError::Unknown)
+    }
+
+    fn update<F>(&mut self, f: F) -> std::result::Result<Value, RpcError>
+            where F: FnOnce(&mut Context) -> Result<bool> {
+        if let Err(e) = self.context() {
+            return Err(e.into());
+        }
+        let context = match self.context.as_mut() {
+            Some(c) => c,
+            None => return Err(RrhError::Unknown.into()),
+        };
+        let result = context.update(f);
+        self.stamp = stamp_of(context);
+        result.map(|_| Value::Bool(true)).map_err(|e| e.into())
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(-32602, e.to_string()))
+}
+
+fn error_response(id: Value, e: RpcError) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": e.code, "message": e.message}})
+}
+
+fn summaries(context: &Context, repos: Vec<Repository>) -> Value {
+    let list = repos.into_iter()
+        .map(|r| RepositorySummary::new(r, context.db.as_ref()))
+        .collect::<Vec<_>>();
+    json!(list)
+}
+
+fn stamp_of(context: &Context) -> Stamp {
+    (
+        config::fingerprint(&context.config.database_path),
+        context.config.from.as_deref().and_then(config::fingerprint),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::TempDir;
+
+    use super::*;
+
+    fn session(config_file: &str) -> Session {
+        Session::new(Loader { config_file: Some(PathBuf::from(config_file)), profile: None })
+    }
+
+    fn request(session: &mut Session, line: &str) -> Value {
+        session.handle(line).unwrap()
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut s = session("testdata/config.json");
+        let v = request(&mut s, r#"{"jsonrpc": "2.0", "id": 1, "method": "list"}"#);
+        assert_eq!(v["id"], 1);
+        assert_eq!(v["result"][0]["id"], "fibonacci");
+        let v = request(&mut s, r#"{"jsonrpc": "2.0", "id": 2, "method": "groups"}"#);
+        assert_eq!(v["result"][0]["repositories"][0], "fibonacci");
+        let v = request(&mut s, r#"{"jsonrpc": "2.0", "id": 3, "method": "find", "params": {"keyword": "FIBO"}}"#);
+        assert_eq!(v["result"].as_array().unwrap().len(), 1);
+        let v = request(&mut s, r#"{"jsonrpc": "2.0", "id": 3, "method": "list", "params": {"select": "id~world$"}}"#);
+        assert_eq!(v["result"][0]["id"], "helloworld");
+        let v = request(&mut s, r#"{"jsonrpc": "2.0", "id": 4, "method": "info", "params": {"id": "unknown"}}"#);
+        assert_eq!(v["error"]["code"], -32000);
+        assert!(s.handle(r#"{"jsonrpc": "2.0", "method": "list"}"#).is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut s = session("testdata/config.json");
+        assert_eq!(request(&mut s, "{")["error"]["code"], -32700);
+        assert_eq!(request(&mut s, r#"{"id": 1}"#)["error"]["code"], -32600);
+        assert_eq!(request(&mut s, r#"{"id": 1, "method": "nothing"}"#)["error"]["code"], -32601);
+        assert_eq!(request(&mut s, r#"{"id": 1, "method": "find"}"#)["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn test_limiter() {
+        let limiter = Limiter::default();
+        let mut permits = (0..MAX_CONNECTIONS).map(|_| limiter.acquire()).collect::<Vec<_>>();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let waiting = limiter.clone();
+        std::thread::spawn(move || sender.send(waiting.acquire()).unwrap());
+        let timeout = std::time::Duration::from_millis(100);
+        assert!(receiver.recv_timeout(timeout).is_err());
+        permits.pop();
+        assert!(receiver.recv_timeout(timeout * 50).is_ok());
+    }
+
+    #[test]
+    fn test_mutations() {
+        let dir = TempDir::new("serve");
+        let repo = dir.join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        let config_path = dir.bootstrap();
+        let mut s = session(config_path.to_str().unwrap());
+
+        let add = json!({"id": 1, "method": "add", "params": {"path": repo, "id": "repo"}});
+        assert_eq!(request(&mut s, &add.to_string())["result"], true);
+        let v = request(&mut s, r#"{"id": 2, "method": "relate", "params": {"id": "repo", "group": "no-group"}}"#);
+        assert_eq!(v["result"], true);
+        let other = Context::new_with_path(config_path.clone()).unwrap();
+        assert!(other.db.find_repository("repo").is_some());
+        let v = request(&mut s, r#"{"id": 3, "method": "remove", "params": {"id": "repo"}}"#);
+        assert_eq!(v["result"], true);
+        assert_eq!(request(&mut s, r#"{"id": 4, "method": "list"}"#)["result"], json!([]));
+        let mut other = Context::new_with_path(config_path.clone()).unwrap();
+        other.db.register(Repository::new("other".into(), repo.clone(), None), vec![]).unwrap();
+        other.store().unwrap();
+        assert_eq!(request(&mut s, r#"{"id": 4, "method": "list"}"#)["result"][0]["id"], "other");
+        let mut buf = Vec::new();
+        s.serve(BufReader::new(&b"{\"id\": 5, \"method\": \"groups\"}\n"[..]), &mut buf).unwrap();
+        assert!(String::from_utf8(buf).unwrap().ends_with("}\n"));
+    }
+}
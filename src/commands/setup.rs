@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use inquire::{InquireError, Select, Text};
+
+use crate::cli::{Result, RrhError, SetupOpts};
+use crate::config::{self, keys, Config, EnvValue};
+
+pub(crate) fn perform_setup(config_file: Option<PathBuf>, opts: SetupOpts) -> Result<bool> {
+    let path = config_file.unwrap_or_else(config::default_config_path);
+    if path.exists() && !opts.force {
+        return Err(RrhError::CliOptsInvalid(
+            "setup".into(),
+            format!("{}: config already exists (use --force to overwrite)", path.display()),
+        ));
+    }
+    let mut config = Config::default_at(path.clone());
+    if opts.interactive {
+        if let Err(e) = inquire_settings(&mut config) {
+            return Err(e);
+        }
+    }
+    match config::bootstrap(&config) {
+        Ok(_) => {
+            println!("config: {}", path.display());
+            println!("database: {}", config.database_path.display());
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn inquire_settings(config: &mut Config) -> Result<()> {
+    let default = |name: &str| config.value_or_default(name).map(|v| v.to_string()).unwrap_or_default();
+    let clone_dir = Text::new("Clone directory:")
+        .with_default(&default("clone_directory"))
+        .with_help_message("the directory for the cloned repositories (`~` is available)")
+        .prompt();
+    let clone_dir = answer(clone_dir)?;
+    let group = Text::new("Default group:")
+        .with_default(&default("default_group"))
+        .with_help_message("the group for the repositories registered without any groups")
+        .prompt();
+    let group = answer(group)?;
+    let current = default("print_list_style");
+    let cursor = keys::LIST_STYLES.iter().position(|s| *s == current).unwrap_or(0);
+    let style = Select::new("List style:", keys::LIST_STYLES.to_vec())
+        .with_starting_cursor(cursor)
+        .prompt();
+    let style = answer(style)?;
+
+    config.envs.insert("clone_directory".into(), EnvValue::of(&clone_dir));
+    config.envs.insert("default_group".into(), EnvValue::of(&group));
+    config.envs.insert("print_list_style".into(), EnvValue::of(style));
+    Ok(())
+}
+
+fn answer<T>(r: std::result::Result<T, InquireError>) -> Result<T> {
+    match r {
+        Ok(v) => Ok(v),
+        Err(InquireError::OperationCanceled) | Err(InquireError::OperationInterrupted) => {
+            Err(RrhError::CliOptsInvalid("setup".into(), "canceled".into()))
+        }
+        Err(InquireError::IO(e)) => Err(RrhError::IO(e)),
+        Err(e) => Err(RrhError::Fatal(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Context, TempDir};
+
+    use super::*;
+
+    #[test]
+    fn test_setup() {
+        let dir = TempDir::new("setup");
+        let path = dir.join("config.json");
+        let opts = SetupOpts { interactive: false, force: false };
+        assert!(!perform_setup(Some(path.clone()), opts.clone()).unwrap());
+        assert!(dir.join("database.json").exists());
+
+        let c = Context::new_with_path(path.clone()).unwrap();
+        assert_eq!(c.db.repositories().unwrap().len(), 0);
+        assert!(matches!(c.config.get_env("default_group"), Some(EnvValue::Var(s)) if s == "no-group"));
+
+        assert!(perform_setup(Some(path.clone()), opts).is_err());
+        let opts = SetupOpts { interactive: false, force: true };
+        assert!(perform_setup(Some(path), opts).is_ok());
+    }
+}
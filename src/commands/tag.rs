@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+use crate::cli::{Result, RrhError, TagListOpts, TagOpts, TagSubCommand, TagUpdateOpts};
+use crate::config::Context;
+use crate::selector::Selector;
+
+pub(crate) fn perform(context: &mut Context, opts: TagOpts) -> Result<bool> {
+    match opts.subcmd {
+        TagSubCommand::Add(opts) => perform_add(context, opts),
+        TagSubCommand::Remove(opts) => perform_remove(context, opts),
+        TagSubCommand::List(opts) => perform_list(context, opts),
+    }
+}
+
+fn perform_add(context: &mut Context, opts: TagUpdateOpts) -> Result<bool> {
+    let errs = opts.tags.iter()
+        .filter(|t| !is_valid_tag(t))
+        .map(|t| RrhError::CliOptsInvalid("tag".into(), format!("{}: invalid tag", t)))
+        .collect::<Vec<_>>();
+    if !errs.is_empty() {
+        return Err(RrhError::Arrays(errs));
+    }
+    let mut repo = match context.db.find_repository(&opts.repository_id) {
+        Some(r) => r,
+        None => return Err(RrhError::RepositoryNotFound(opts.repository_id)),
+    };
+    for tag in opts.tags.iter() {
+        repo.add_tag(tag);
+    }
+    context.db.update_repository(opts.repository_id, repo).map(|_| true)
+}
+
+fn perform_remove(context: &mut Context, opts: TagUpdateOpts) -> Result<bool> {
+    let mut repo = match context.db.find_repository(&opts.repository_id) {
+        Some(r) => r,
+        None => return Err(RrhError::RepositoryNotFound(opts.repository_id)),
+    };
+    let mut removed = false;
+    for tag in opts.tags.iter() {
+        if repo.remove_tag(tag) {
+            removed = true;
+        } else {
+            log::warn!("{}: tag not found in {}", tag, repo.id);
+        }
+    }
+    if !removed {
+        return Ok(false);
+    }
+    context.db.update_repository(opts.repository_id, repo).map(|_| true)
+}
+
+fn perform_list(context: &Context, opts: TagListOpts) -> Result<bool> {
+    let selector = match Selector::from_options(&[], opts.select.select.as_deref()) {
+        Ok(s) => s,
+        Err(e) => return Err(e),
+    };
+    let tags = match tagged_repositories(context, &opts.ids, selector.as_ref()) {
+        Ok(tags) => tags,
+        Err(e) => return Err(e),
+    };
+    let mut builder = Builder::new();
+    builder.push_record(vec!["Tag", "Repositories"]);
+    for (tag, ids) in tags {
+        builder.push_record(vec![tag, ids.join(", ")]);
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    println!("{}", table);
+    Ok(false)
+}
+
+/// returns the tags and the ids of their repositories in the order of the tags.
+fn tagged_repositories(context: &Context, ids: &[String], selector: Option<&Selector>) -> Result<BTreeMap<String, Vec<String>>> {
+    let repos = if ids.is_empty() {
+        match context.db.repositories() {
+            Ok(repos) => repos,
+            Err(e) => return Err(e),
+        }
+    } else {
+        let mut repos = vec![];
+        for id in ids {
+            match context.db.find_repository(id) {
+                Some(r) => repos.push(r),
+                None => return Err(RrhError::RepositoryNotFound(id.clone())),
+            }
+        }
+        repos
+    };
+    let mut result = BTreeMap::<String, Vec<String>>::new();
+    for repo in repos.into_iter().filter(|r| selector.is_none_or(|s| s.matches(r, context.db.as_ref()))) {
+        for tag in repo.tags.iter() {
+            result.entry(tag.clone()).or_default().push(repo.id.clone());
+        }
+    }
+    Ok(result)
+}
+
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && !tag.starts_with('=')
+        && !tag.chars().any(|c| c.is_whitespace() || c == ',')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn update_opts(id: &str, tags: Vec<&str>) -> TagUpdateOpts {
+        TagUpdateOpts {
+            repository_id: id.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        perform_add(&mut context, update_opts("fibonacci", vec!["lang=go", "oncall"])).unwrap();
+        perform_add(&mut context, update_opts("fibonacci", vec!["lang=rust", "archived"])).unwrap();
+        let repo = context.db.find_repository("fibonacci").unwrap();
+        assert_eq!(repo.tags, vec!["oncall", "lang=rust", "archived"]);
+        assert!(repo.has_tags(&[String::from("lang"), String::from("oncall")]));
+        assert!(!repo.has_tag("lang=go"));
+        assert!(perform_add(&mut context, update_opts("fibonacci", vec!["bad tag"])).is_err());
+
+        assert!(perform_remove(&mut context, update_opts("fibonacci", vec!["lang", "oncall"])).unwrap());
+        assert!(!perform_remove(&mut context, update_opts("fibonacci", vec!["oncall"])).unwrap());
+        let tags = tagged_repositories(&context, &[], None).unwrap();
+        assert_eq!(tags.keys().collect::<Vec<_>>(), vec!["archived"]);
+        assert_eq!(tags["archived"], vec!["fibonacci"]);
+        let selector = Selector::parse("id:hello*").unwrap();
+        assert!(tagged_repositories(&context, &[], Some(&selector)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+use std::io::Write;
+
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::{IntoAlternateScreen, ToAlternateScreen, ToMainScreen};
+use termion::{clear, cursor, style};
+
+use crate::alias::AliasManager;
+use crate::cli::{OpenTarget, PruneOpts, RenameOpts, Result, RrhError, TuiOpts};
+use crate::commands::{exec, prune, repository};
+use crate::config::Context;
+use crate::entities::{Group, Repository};
+use crate::utils;
+
+pub(crate) fn perform_tui(context: &mut Context, c: TuiOpts) -> Result<bool> {
+    let mut dashboard = match Dashboard::new(context) {
+        Ok(d) => d,
+        Err(e) => return Err(e),
+    };
+    if let Some(name) = c.group {
+        match dashboard.groups.iter().position(|g| g.name == name) {
+            Some(i) => dashboard.select_group(context, i),
+            None => return Err(RrhError::GroupNotFound(name)),
+        }
+    }
+    dashboard.run(context)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Groups,
+    Repositories,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Prompt {
+    Alias,
+    Rename,
+    Move,
+    Prune,
+}
+
+impl Prompt {
+    fn label(&self) -> &'static str {
+        match self {
+            Prompt::Alias => "alias name: ",
+            Prompt::Rename => "rename to: ",
+            Prompt::Move => "move to group: ",
+            Prompt::Prune => "prune empty groups and missing repositories? (y/N): ",
+        }
+    }
+}
+
+enum Action {
+    Continue,
+    Quit,
+    RunAlias(String),
+}
+
+/// two-pane dashboard: the groups on the left, and the repositories of the selected group on the right.
+struct Dashboard {
+    groups: Vec<Group>,
+    repos: Vec<Repository>,
+    pane: Pane,
+    group_cursor: usize,
+    repo_cursor: usize,
+    prompt: Option<(Prompt, String)>,
+    message: String,
+    status: Option<(String, Vec<String>)>,
+    dirty: bool,
+}
+
+impl Dashboard {
+    fn new(context: &Context) -> Result<Self> {
+        let mut d = Self {
+            groups: vec![],
+            repos: vec![],
+            pane: Pane::Groups,
+            group_cursor: 0,
+            repo_cursor: 0,
+            prompt: None,
+            message: String::new(),
+            status: None,
+            dirty: false,
+        };
+        match d.reload(context) {
+            Ok(_) => Ok(d),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn reload(&mut self, context: &Context) -> Result<()> {
+        self.groups = context.db.groups()?;
+        if self.group_cursor >= self.groups.len() {
+            self.group_cursor = self.groups.len().saturating_sub(1);
+        }
+        self.repos = match self.groups.get(self.group_cursor) {
+            Some(g) => context.db.find_repositories_of(&g.name)?,
+            None => vec![],
+        };
+        if self.repo_cursor >= self.repos.len() {
+            self.repo_cursor = self.repos.len().saturating_sub(1);
+        }
+        if self.repos.is_empty() {
+            self.pane = Pane::Groups;
+        }
+        Ok(())
+    }
+
+    fn select_group(&mut self, context: &Context, index: usize) {
+        self.group_cursor = index;
+        self.repo_cursor = 0;
+        if let Err(e) = self.reload(context) {
+            self.message = format!("{:?}", e);
+        }
+    }
+
+    fn current_group(&self) -> Option<&Group> {
+        self.groups.get(self.group_cursor)
+    }
+
+    fn current_repository(&self) -> Option<&Repository> {
+        self.repos.get(self.repo_cursor)
+    }
+
+    fn run(&mut self, context: &mut Context) -> Result<bool> {
+        let tty = termion::get_tty().map_err(RrhError::IO)?;
+        let input = tty.try_clone().map_err(RrhError::IO)?;
+        let mut screen = tty
+            .into_raw_mode()
+            .and_then(|t| t.into_alternate_screen())
+            .map_err(RrhError::IO)?;
+        let mut keys = input.try_clone().map_err(RrhError::IO)?.keys();
+        loop {
+            let size = termion::terminal_size_fd(&input).unwrap_or((80, 24));
+            self.render(&mut screen, size).map_err(RrhError::IO)?;
+            let key = match keys.next() {
+                Some(k) => k.map_err(RrhError::IO)?,
+                None => break,
+            };
+            match self.handle_key(context, key) {
+                Action::Continue => {}
+                Action::Quit => break,
+                Action::RunAlias(name) => {
+                    let _ = write!(screen, "{}{}", cursor::Show, ToMainScreen);
+                    let _ = screen.flush();
+                    let _ = screen.suspend_raw_mode();
+                    self.message = match self.run_alias(context, &name) {
+                        Ok(_) => format!("alias {}: done", name),
+                        Err(e) => format!("alias {}: {:?}", name, e),
+                    };
+                    println!("\n[press any key to return]");
+                    let _ = screen.activate_raw_mode();
+                    let _ = keys.next();
+                    let _ = write!(screen, "{}", ToAlternateScreen);
+                }
+            }
+        }
+        let _ = write!(screen, "{}", cursor::Show);
+        let _ = screen.flush();
+        Ok(self.dirty)
+    }
+
+    /// the alias runs with its own context, so the pending changes are stored before
+    /// and the database is reloaded after the execution.
+    fn run_alias(&mut self, context: &mut Context, name: &str) -> Result<()> {
+        let alias = match context.config.find(name.to_string()) {
+            Some(a) => a,
+            None => return Err(RrhError::Arguments(format!("{}: alias not found", name))),
+        };
+        if self.dirty {
+            context.store()?;
+            self.dirty = false;
+        }
+        let result = alias.execute(context, vec![]);
+        if let Some(from) = context.config.from.clone() {
+            *context = Context::new_with_path(from)?;
+        }
+        self.reload(context)?;
+        result
+    }
+
+    fn handle_key(&mut self, context: &mut Context, key: Key) -> Action {
+        if let Some((prompt, input)) = self.prompt.clone() {
+            return self.handle_prompt_key(context, prompt, input, key);
+        }
+        self.message.clear();
+        match key {
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Action::Quit,
+            Key::Char('\t') | Key::Left | Key::Right | Key::Char('h') | Key::Char('l') => self.switch_pane(),
+            Key::Up | Key::Char('k') => self.move_cursor(context, -1),
+            Key::Down | Key::Char('j') => self.move_cursor(context, 1),
+            Key::Char('o') => self.open(OpenTarget::Folder),
+            Key::Char('w') => self.open(OpenTarget::Webpage),
+            Key::Char('a') => self.prompt = Some((Prompt::Alias, String::new())),
+            Key::Char('r') => self.prompt = Some((Prompt::Rename, String::new())),
+            Key::Char('m') if self.pane == Pane::Repositories => {
+                self.prompt = Some((Prompt::Move, String::new()))
+            }
+            Key::Char('p') => self.prompt = Some((Prompt::Prune, String::new())),
+            Key::Char('s') => self.update_status(),
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn handle_prompt_key(&mut self, context: &mut Context, prompt: Prompt, mut input: String, key: Key) -> Action {
+        match key {
+            Key::Esc | Key::Ctrl('c') => self.prompt = None,
+            Key::Char('\n') => {
+                self.prompt = None;
+                if let Some(action) = self.submit(context, prompt, input.trim().to_string()) {
+                    return action;
+                }
+            }
+            Key::Backspace => {
+                input.pop();
+                self.prompt = Some((prompt, input));
+            }
+            Key::Char(c) => {
+                input.push(c);
+                self.prompt = Some((prompt, input));
+            }
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn submit(&mut self, context: &mut Context, prompt: Prompt, input: String) -> Option<Action> {
+        if input.is_empty() && prompt != Prompt::Prune {
+            return None;
+        }
+        let result = match prompt {
+            Prompt::Alias => return Some(Action::RunAlias(input)),
+            Prompt::Rename => self.rename(context, input),
+            Prompt::Move => self.move_repository(context, input),
+            Prompt::Prune if input.to_lowercase().starts_with('y') => {
+                let opts = PruneOpts { inquiry: false, dry_run: false };
+                prune::perform_prune(context, opts)
+            }
+            Prompt::Prune => return None,
+        };
+        match result {
+            Ok(changed) => {
+                self.dirty = self.dirty || changed;
+                self.message = String::from("done");
+            }
+            Err(e) => self.message = format!("{:?}", e),
+        }
+        if let Err(e) = self.reload(context) {
+            self.message = format!("{:?}", e);
+        }
+        None
+    }
+
+    fn rename(&mut self, context: &mut Context, to_name: String) -> Result<bool> {
+        let (from, repository) = match self.pane {
+            Pane::Groups => match self.current_group() {
+                Some(g) => (g.name.clone(), false),
+                None => return Ok(false),
+            },
+            Pane::Repositories => match self.current_repository() {
+                Some(r) => (r.id.clone(), true),
+                None => return Ok(false),
+            },
+        };
+        let opts = RenameOpts { repository, group: !repository, from, to_name, dry_run: false };
+        prune::perform_rename(context, opts)
+    }
+
+    fn move_repository(&mut self, context: &mut Context, to_group: String) -> Result<bool> {
+        let (repo_id, from_group) = match (self.current_repository(), self.current_group()) {
+            (Some(r), Some(g)) => (r.id.clone(), g.name.clone()),
+            _ => return Ok(false),
+        };
+        if from_group == to_group {
+            return Ok(false);
+        }
+        repository::relate_with(context, &to_group, &repo_id)?;
+        context.db.delete_relation(repo_id, from_group.clone())?;
+        repository::delete_empty_groups(context, vec![from_group])?;
+        Ok(true)
+    }
+
+    fn open(&mut self, target: OpenTarget) {
+        let repos = match self.pane {
+            Pane::Groups => self.repos.clone(),
+            Pane::Repositories => self.current_repository().cloned().into_iter().collect(),
+        };
+        for repo in repos {
+            if let Err(e) = exec::open_repository(&target, &repo) {
+                self.message = format!("{}: {:?}", repo.id, e);
+            }
+        }
+    }
+
+    fn update_status(&mut self) {
+        self.status = self.current_repository().map(|r| {
+            let lines = match utils::git_status(&r.path) {
+                Ok(lines) => lines,
+                Err(e) => vec![format!("(git status unavailable: {:?})", e)],
+            };
+            (r.id.clone(), lines)
+        });
+    }
+
+    fn switch_pane(&mut self) {
+        self.pane = match self.pane {
+            Pane::Groups if !self.repos.is_empty() => Pane::Repositories,
+            _ => Pane::Groups,
+        };
+        self.update_status();
+    }
+
+    fn move_cursor(&mut self, context: &Context, delta: isize) {
+        match self.pane {
+            Pane::Groups => {
+                if let Some(i) = moved(self.group_cursor, delta, self.groups.len()) {
+                    self.select_group(context, i);
+                }
+            }
+            Pane::Repositories => {
+                if let Some(i) = moved(self.repo_cursor, delta, self.repos.len()) {
+                    self.repo_cursor = i;
+                    self.update_status();
+                }
+            }
+        }
+    }
+
+    fn render(&self, out: &mut impl Write, (cols, rows): (u16, u16)) -> std::io::Result<()> {
+        let cols = cols as usize;
+        let rows = rows.max(8) as usize;
+        let left = (cols / 3).clamp(10, 30);
+        let right = cols.saturating_sub(left + 3);
+        let body = rows - 2;
+        let list_height = body / 2;
+        write!(out, "{}{}{}", cursor::Hide, clear::All, cursor::Goto(1, 1))?;
+        write!(out, "{}{}{}", style::Bold, truncate(" rrh dashboard", cols), style::Reset)?;
+
+        for (i, g) in self.groups.iter().enumerate().skip(scroll(self.group_cursor, body)).take(body) {
+            let line = (i - scroll(self.group_cursor, body) + 2) as u16;
+            let text = truncate(&format!(" {}", g.name), left);
+            write!(out, "{}", cursor::Goto(1, line))?;
+            self.write_item(out, &text, i == self.group_cursor, self.pane == Pane::Groups)?;
+        }
+        for line in 2..rows {
+            write!(out, "{}|", cursor::Goto((left + 2) as u16, line as u16))?;
+        }
+        let x = (left + 4) as u16;
+        for (i, r) in self.repos.iter().enumerate().skip(scroll(self.repo_cursor, list_height)).take(list_height) {
+            let line = (i - scroll(self.repo_cursor, list_height) + 2) as u16;
+            let text = truncate(&format!(" {}", r.id), right);
+            write!(out, "{}", cursor::Goto(x, line))?;
+            self.write_item(out, &text, i == self.repo_cursor, self.pane == Pane::Repositories)?;
+        }
+        write!(out, "{}{}", cursor::Goto(x, (list_height + 2) as u16), "-".repeat(right))?;
+        for (i, line) in self.details().iter().take(body - list_height - 1).enumerate() {
+            write!(out, "{}{}", cursor::Goto(x, (list_height + 3 + i) as u16), truncate(line, right))?;
+        }
+        write!(out, "{}", cursor::Goto(1, rows as u16))?;
+        match &self.prompt {
+            Some((p, input)) => write!(out, "{}{}", truncate(&format!("{}{}", p.label(), input), cols), cursor::Show)?,
+            None if !self.message.is_empty() => write!(out, "{}", truncate(&self.message, cols))?,
+            None => write!(out, "{}", truncate(
+                "q:quit tab:pane j/k:move o:open w:web a:alias r:rename m:move p:prune s:status", cols))?,
+        }
+        out.flush()
+    }
+
+    fn write_item(&self, out: &mut impl Write, text: &str, current: bool, focused: bool) -> std::io::Result<()> {
+        if current && focused {
+            write!(out, "{}{}{}", style::Invert, text, style::Reset)
+        } else if current {
+            write!(out, "{}{}{}", style::Underline, text, style::Reset)
+        } else {
+            write!(out, "{}", text)
+        }
+    }
+
+    fn details(&self) -> Vec<String> {
+        match self.pane {
+            Pane::Groups => match self.current_group() {
+                Some(g) => vec![
+                    format!("Group:  {}", g.name),
+                    format!("Note:   {}", g.note),
+                    format!("Abbrev: {}", g.is_abbrev()),
+                    format!("Count:  {}", utils::format_humanize(self.repos.len(), "repository", "repositories")),
+                ],
+                None => vec![String::from("no groups")],
+            },
+            Pane::Repositories => match self.current_repository() {
+                Some(r) => {
+                    let mut lines = vec![
+                        format!("ID:          {}", r.id),
+                        format!("Path:        {}", r.path.to_string_lossy()),
+                        format!("Description: {}", r.description.clone().unwrap_or_default()),
+                        String::new(),
+                    ];
+                    if let Some((id, status)) = &self.status {
+                        if *id == r.id {
+                            lines.extend(status.clone());
+                        }
+                    }
+                    lines
+                }
+                None => vec![],
+            },
+        }
+    }
+}
+
+fn moved(current: usize, delta: isize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let next = (current as isize + delta).clamp(0, len as isize - 1) as usize;
+    if next == current {
+        None
+    } else {
+        Some(next)
+    }
+}
+
+fn scroll(cursor: usize, height: usize) -> usize {
+    if height == 0 || cursor < height {
+        0
+    } else {
+        cursor + 1 - height
+    }
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_move_repository() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut d = Dashboard::new(&context).unwrap();
+        assert_eq!(d.repos.len(), 2);
+        d.handle_key(&mut context, Key::Char('\t'));
+        assert_eq!(d.pane, Pane::Repositories);
+        d.handle_key(&mut context, Key::Char('m'));
+        for c in "moved\n".chars() {
+            d.handle_key(&mut context, Key::Char(c));
+        }
+        assert!(d.dirty);
+        assert!(context.db.has_relation("fibonacci", "moved"));
+        assert!(!context.db.has_relation("fibonacci", "no-group"));
+        assert_eq!(d.repos.len(), 1);
+    }
+
+    #[test]
+    fn test_moved() {
+        assert_eq!(moved(0, -1, 3), None);
+        assert_eq!(moved(0, 1, 3), Some(1));
+        assert_eq!(moved(2, 1, 3), None);
+        assert_eq!(moved(0, 1, 0), None);
+    }
+}
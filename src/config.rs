@@ -4,120 +4,443 @@ use dotenv::dotenv;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
-use std::fs::File;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
 use crate::cli::{Result, RrhError};
 use crate::db::jsondb::JsonDB;
+use crate::db::layered::{Layer, LayeredDB};
 use crate::db::Database;
+use crate::{logger, utils};
 
-pub(crate) struct Context {
-    pub(crate) config: Config,
-    pub(crate) db: Box<dyn Database>,
+mod format;
+pub(crate) mod keys;
+pub(crate) mod profile;
+
+pub(crate) use format::ConfigFormat;
+
+/// the loaded configuration and database, the entry point of the library.
+pub struct Context {
+    pub config: Config,
+    pub db: Box<dyn Database>,
+    /// the fingerprint of the database file on loading, for detecting the updates by the other processes.
+    loaded: Option<u64>,
 }
 
 impl Context {
-    pub(crate) fn new() -> Result<Self> {
+    /// loads the context of the default profile, and creates the config at the first time.
+    pub fn new() -> Result<Self> {
         Context::new_with_config(Config::new())
     }
 
-    pub(crate) fn new_with_path(path: PathBuf) -> Result<Self> {
+    pub fn new_with_path(path: PathBuf) -> Result<Self> {
         Context::new_with_config(Config::new_with_path(path))
     }
 
+    pub fn new_with_profile(name: &str) -> Result<Self> {
+        if name == profile::DEFAULT_PROFILE {
+            Context::new()
+        } else if profile::exists(name) {
+            Context::new_with_path(profile::config_path_of(name))
+        } else {
+            Err(RrhError::ProfileNotFound(name.to_string()))
+        }
+    }
+
     fn new_with_config(loaded_config: Result<Config>) -> Result<Self> {
         match loaded_config {
-            Ok(config) => match load_db(&config) {
-                Ok(db) => Ok(Self { config, db }),
-                Err(e) => Err(e),
-            },
+            Ok(config) => {
+                let loaded = fingerprint(&config.database_path);
+                match load_db(&config) {
+                    Ok(db) => Ok(Self { config, db, loaded }),
+                    Err(e) => Err(e),
+                }
+            }
             Err(e) => Err(e),
         }
     }
 
-    pub(crate) fn store(&mut self) -> Result<()> {
+    /// stores the database and the config (only if modified).
+    /// It fails with `DatabaseModified` if the other process stored the database after loading,
+    /// so that the update of the other process is not overwritten.
+    pub fn store(&mut self) -> Result<()> {
+        let _lock = match DatabaseLock::acquire(&self.config.database_path) {
+            Ok(lock) => lock,
+            Err(e) => return Err(e),
+        };
+        if fingerprint(&self.config.database_path) != self.loaded {
+            return Err(RrhError::DatabaseModified(self.config.database_path.clone()));
+        }
+        self.store_locked()
+    }
+
+    /// reloads the database, applies the given update, and stores it while holding the lock.
+    /// The long-running processes (e.g., `rrh serve`) use this for the updates from the other processes.
+    pub fn update<T, F>(&mut self, f: F) -> Result<T>
+            where F: FnOnce(&mut Context) -> Result<T> {
+        let _lock = match DatabaseLock::acquire(&self.config.database_path) {
+            Ok(lock) => lock,
+            Err(e) => return Err(e),
+        };
+        self.loaded = fingerprint(&self.config.database_path);
+        match load_db(&self.config) {
+            Ok(db) => self.db = db,
+            Err(e) => return Err(e),
+        }
+        let result = match f(self) {
+            Ok(r) => r,
+            Err(e) => return Err(e),
+        };
+        self.store_locked().map(|_| result)
+    }
+
+    /// stores only the config (if modified), for the commands which do not update the database.
+    pub(crate) fn store_config(&self) -> Result<()> {
+        store_config(&self.config)
+    }
+
+    fn store_locked(&mut self) -> Result<()> {
+        if self.config.is_env_value_true("sort_on_update") == Some(true) {
+            self.db.sort();
+        }
         if let Err(e) = store_db(&self.config, &mut self.db) {
             return Err(e);
         }
+        self.loaded = fingerprint(&self.config.database_path);
         store_config(&self.config)
     }
 }
 
+/// returns the hash of the file (e.g., the database), or `None` if it does not exist.
+/// The contents are compared instead of the modified time, since its resolution is too coarse.
+pub(crate) fn fingerprint(path: &Path) -> Option<u64> {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let data = std::fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
 fn load_db(config: &Config) -> Result<Box<dyn Database>> {
-    println!("load_db({})", config.database_path.display());
-    match JsonDB::load(config.database_path.clone()) {
-        Ok(db) => Ok(Box::new(db)),
-        Err(e) => {
-            println!("load db error: {:?}", e);
-            Err(e)
+    let db = match load_json_db(config) {
+        Ok(db) => db,
+        Err(e) => return Err(e),
+    };
+    let mut layers = Vec::new();
+    for name in config.stack.iter() {
+        match load_layer(name) {
+            Ok(layer) => layers.push(Layer::new(name, Box::new(layer))),
+            Err(e) => return Err(e),
+        }
+    }
+    match load_shared_layer(config) {
+        Some(Ok(shared)) => layers.push(Layer::new("shared", Box::new(shared))),
+        Some(Err(e)) => return Err(e),
+        None => {}
+    }
+    if layers.is_empty() {
+        return Ok(Box::new(db));
+    }
+    let db = LayeredDB::new(Box::new(db), layers);
+    for (id, layer) in db.conflicts() {
+        log::warn!("{}: conflicts with the repository in the {} database (the personal one is used)", id, layer);
+    }
+    Ok(Box::new(db))
+}
+
+/// loads the database and resolves the roots (e.g., `${HOME}`) in the paths of the repositories.
+fn load_json_db(config: &Config) -> Result<JsonDB> {
+    if !config.database_path.exists() {
+        log::info!("{}: database not found, use the empty database", config.database_path.display());
+        return Ok(JsonDB::default());
+    }
+    let label = format!("load the database: {}", config.database_path.display());
+    logger::timed(&label, || JsonDB::load(config.database_path.clone())).map(|mut db| {
+        db.map_paths(&|p| config.resolve_path(p));
+        db
+    })
+}
+
+/// loads the shared database from `shared_database` (the file, or the directory such as
+/// the checkout of a git repository containing `database.json`).
+/// The relative paths in the shared database are resolved against `workspace_root`.
+fn load_shared_layer(config: &Config) -> Option<Result<JsonDB>> {
+    let path = match config.value(String::from("shared_database")).map(|v| v.to_string()) {
+        Some(p) if !p.is_empty() => utils::expand_home(&p),
+        _ => return None,
+    };
+    let path = if path.is_dir() { path.join("database.json") } else { path };
+    let root = match config.value_or_default("workspace_root") {
+        Some(root) => utils::expand_home(&root.to_string()),
+        None => PathBuf::from("."),
+    };
+    let label = format!("load the shared database: {}", path.display());
+    Some(logger::timed(&label, || JsonDB::load(path.clone())).map(|mut db| {
+        db.map_paths(&|p| config.resolve_path(p));
+        db.rebase(&root);
+        db
+    }))
+}
+
+/// loads the database of the given profile as a read-only layer.
+/// The stack of the stacked profile is not followed.
+fn load_layer(name: &str) -> Result<JsonDB> {
+    if !profile::exists(name) {
+        return Err(RrhError::ProfileNotFound(name.to_string()));
+    }
+    match load_config(profile::config_path_of(name)) {
+        Ok(config) => load_json_db(&config),
+        Err(e) => Err(e),
+    }
+}
+
+/// the lock file next to the database (`database.json.lock`), which serializes the updates
+/// of the processes. The lock left by the crashed process is removed after `LOCK_STALE`.
+struct DatabaseLock {
+    path: PathBuf,
+}
+
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_STALE: Duration = Duration::from_secs(60);
+
+impl DatabaseLock {
+    fn acquire(database_path: &Path) -> Result<Self> {
+        if let Err(e) = create_parent_dir(database_path) {
+            return Err(e);
+        }
+        let mut name = database_path.as_os_str().to_os_string();
+        name.push(".lock");
+        let path = PathBuf::from(name);
+        let start = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(RrhError::IO(e)),
+                Err(_) => {}
+            }
+            let stale = path.metadata()
+                .and_then(|m| m.modified())
+                .map(|t| t.elapsed().unwrap_or_default() > LOCK_STALE)
+                .unwrap_or(false);
+            if stale {
+                log::warn!("{}: remove the stale lock", path.display());
+                let _ = std::fs::remove_file(&path);
+            } else if start.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                return Err(RrhError::DatabaseLocked(path));
+            } else {
+                std::thread::sleep(Duration::from_millis(50));
+            }
         }
     }
 }
 
+impl Drop for DatabaseLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// stores the database with the paths collapsed into the roots (e.g., `${HOME}`).
 fn store_db(config: &Config, db: &mut Box<dyn Database>) -> Result<()> {
-    match File::create(&config.database_path) {
-        Ok(file) => db.store(Box::new(file)),
-        Err(e) => Err(RrhError::IO(e)),
+    if let Err(e) = create_parent_dir(&config.database_path) {
+        return Err(e);
     }
+    let file = match File::create(&config.database_path) {
+        Ok(file) => file,
+        Err(e) => return Err(RrhError::IO(e)),
+    };
+    db.map_paths(&|p| config.collapse_path(p));
+    let label = format!("store the database: {}", config.database_path.display());
+    let result = logger::timed(&label, || db.store(Box::new(file)));
+    db.map_paths(&|p| config.resolve_path(p));
+    result
 }
 
 fn store_config(config: &Config) -> Result<()> {
+    if !config.is_modified() {
+        return Ok(());
+    }
     if let Some(from) = &config.from {
-        match File::create(from) {
-            Ok(file) => match serde_json::to_writer(file, config) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(RrhError::Json(e)),
-            },
-            Err(e) => Err(RrhError::IO(e)),
+        if let Err(e) = create_parent_dir(from) {
+            return Err(e);
+        }
+        if let Err(e) = format::check_writable(from, config.format) {
+            return Err(e);
+        }
+        match format::serialize(config, config.format) {
+            Ok(data) => std::fs::write(from, data).map_err(RrhError::IO),
+            Err(e) => Err(e),
         }
     } else {
         Err(RrhError::Fatal("config path was not set".into()))
     }
 }
 
+fn create_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => {
+            std::fs::create_dir_all(dir).map_err(RrhError::IO)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// stores the given config and an empty database (if the database does not exist).
+pub(crate) fn bootstrap(config: &Config) -> Result<()> {
+    if let Err(e) = store_config(config) {
+        return Err(e);
+    }
+    if config.database_path.exists() {
+        return Ok(());
+    }
+    let mut db: Box<dyn Database> = Box::new(JsonDB::default());
+    store_db(config, &mut db)
+}
+
 #[derive(Debug, Deserialize, Serialize)]
-pub(crate) struct Config {
-    pub(crate) home: PathBuf,
+pub struct Config {
+    pub home: PathBuf,
     #[serde(rename = "config_path")]
-    pub(crate) from: Option<PathBuf>,
-    pub(crate) database_path: PathBuf,
-    pub(crate) envs: HashMap<String, EnvValue>,
-    pub(crate) aliases: HashMap<String, Vec<String>>,
+    pub from: Option<PathBuf>,
+    pub database_path: PathBuf,
+    pub envs: HashMap<String, EnvValue>,
+    pub aliases: HashMap<String, Vec<String>>,
+    /// the named roots of the repository paths, e.g., `WORK = "~/work"` for `${WORK}/project`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roots: HashMap<String, String>,
+    /// the profiles whose databases are stacked under the database as read-only layers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+    #[serde(skip)]
+    pub(crate) format: ConfigFormat,
+    #[serde(skip)]
+    snapshot: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
-pub(crate) enum EnvValue {
+pub enum EnvValue {
     Bool(bool),
     Var(String),
     Value(i32),
 }
 
 impl EnvValue {
-    pub(crate) fn of(s: &str) -> Self {
+    pub fn of(s: &str) -> Self {
         Self::Var(s.to_string())
     }
 }
 
+impl std::fmt::Display for EnvValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvValue::Bool(b) => write!(f, "{}", b),
+            EnvValue::Var(s) => write!(f, "{}", s),
+            EnvValue::Value(i) => write!(f, "{}", i),
+        }
+    }
+}
+
 impl Config {
-    pub(crate) fn new() -> Result<Self> {
+    pub fn new() -> Result<Self> {
         dotenv().ok();
-        let config_dir = config_dir();
-        let config_path = if let Ok(p) = env::var("config_path") {
-            PathBuf::from(p)
-        } else {
-            config_dir.join("config.json")
-        };
+        let config_path = default_config_path();
+        if !config_path.exists() {
+            if let Err(e) = bootstrap(&Config::default_at(config_path.clone())) {
+                return Err(e);
+            }
+            log::info!("{}: created the default config", config_path.display());
+        }
         load_config(config_path)
     }
 
-    pub(crate) fn new_with_path(config_path: PathBuf) -> Result<Self> {
+    pub fn new_with_path(config_path: PathBuf) -> Result<Self> {
         dotenv().ok();
         load_config(config_path)
     }
 
-    pub(crate) fn value(&self, key: String) -> Option<EnvValue> {
+    /// builds the default config which will be stored in the given path.
+    /// The database is placed next to the config file.
+    pub fn default_at(config_path: PathBuf) -> Self {
+        let home = match config_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let envs = ["clone_directory", "default_group", "print_list_style"].iter()
+            .filter_map(|name| keys::find_key(name))
+            .map(|key| (key.name.to_string(), key.default_value()))
+            .collect();
+        Config {
+            database_path: home.join("database.json"),
+            home,
+            format: ConfigFormat::of(&config_path),
+            from: Some(config_path),
+            envs,
+            aliases: HashMap::new(),
+            roots: HashMap::new(),
+            stack: vec![],
+            snapshot: None,
+        }
+    }
+
+    /// returns the directory of the given root name.
+    /// `HOME` and the environment variables are available without defining them in `roots`.
+    fn root(&self, name: &str) -> Option<PathBuf> {
+        match self.roots.get(name) {
+            Some(dir) => Some(utils::expand_home(dir)),
+            None if name == "HOME" => dirs::home_dir(),
+            None => env::var(name).ok().filter(|v| !v.is_empty()).map(PathBuf::from),
+        }
+    }
+
+    /// replaces the leading `${NAME}` of the given path with the directory of the root.
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        let s = path.to_string_lossy();
+        let (name, rest) = match s.strip_prefix("${").and_then(|r| r.split_once('}')) {
+            Some(v) => v,
+            None => return path.to_path_buf(),
+        };
+        match self.root(name) {
+            Some(root) => match rest.trim_start_matches('/') {
+                "" => root,
+                rest => root.join(rest),
+            },
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// replaces the longest root directory of the given absolute path with `${NAME}`.
+    pub fn collapse_path(&self, path: &Path) -> PathBuf {
+        if !path.is_absolute() {
+            return path.to_path_buf();
+        }
+        let mut names = vec![String::from("HOME")];
+        names.extend(self.roots.keys().cloned());
+        names.iter()
+            .filter_map(|name| self.root(name).map(|root| (name, root)))
+            .filter_map(|(name, root)| path.strip_prefix(&root).ok().map(|rest| (name, root.components().count(), rest)))
+            .max_by_key(|(_, depth, _)| *depth)
+            .map(|(name, _, rest)| match rest.as_os_str().is_empty() {
+                true => PathBuf::from(format!("${{{}}}", name)),
+                false => PathBuf::from(format!("${{{}}}", name)).join(rest),
+            })
+            .unwrap_or(path.to_path_buf())
+    }
+
+    /// returns true if the config was changed after loading.
+    pub(crate) fn is_modified(&self) -> bool {
+        match &self.snapshot {
+            Some(v) => serde_json::to_value(self).map(|current| current != *v).unwrap_or(true),
+            None => true,
+        }
+    }
+
+    pub fn value(&self, key: String) -> Option<EnvValue> {
         if let Some(v) = self.envs.get(&key) {
             Some(v.clone())
         } else {
@@ -128,6 +451,12 @@ impl Config {
         }
     }
 
+    /// returns the value of the given key, or the default value if the key is the known key.
+    pub fn value_or_default(&self, key: &str) -> Option<EnvValue> {
+        self.value(key.to_string())
+            .or_else(|| keys::find_key(key).map(|k| k.default_value()))
+    }
+
     pub(crate) fn is_old(&self, time: SystemTime) -> bool {
         let duration = if let Some(EnvValue::Value(t)) =
             self.value("last_access_reload_duration_secs".to_string())
@@ -190,7 +519,6 @@ fn format_time(t: SystemTime, v: Option<EnvValue>) -> Option<String> {
             if s == "humanize" || s == "relative"  {
                 Some(format!("{}", HumanTime::from(dt)))
             } else if s.starts_with("strftime(") && s.ends_with(")") {
-                println!("strftime({})", &orig[9..orig.len() - 1]);
                 Some(format!("{}", dt.format(&orig[9..orig.len() - 1])))
             } else if s == "iso" || s == "iso8601" {
                 Some(format!("{}", dt.format("%+")))
@@ -209,22 +537,32 @@ fn format_time(t: SystemTime, v: Option<EnvValue>) -> Option<String> {
 }
 
 fn load_config(config_path: PathBuf) -> Result<Config> {
-    match load_config_impl(config_path.clone()) {
+    match format::parse(&config_path) {
         Ok(mut c) => {
             c.from = Some(config_path);
+            c.snapshot = serde_json::to_value(&c).ok();
             Ok(c)
         }
         Err(e) => Err(e),
     }
 }
 
-fn load_config_impl(config_path: PathBuf) -> Result<Config> {
-    match std::fs::read_to_string(&config_path) {
-        Ok(data) => match serde_json::from_str(&data) {
-            Ok(c) => Ok(c),
-            Err(e) => Err(RrhError::Json(e)),
-        },
-        Err(e) => Err(RrhError::IO(e)),
+/// finds the config file in the given directory with the supported extensions.
+/// If any file is not found, returns the path of `config.json`.
+fn find_config_file(dir: &PathBuf) -> PathBuf {
+    ConfigFormat::candidates()
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|p| p.exists())
+        .unwrap_or(dir.join("config.json"))
+}
+
+/// the config path from `config_path` env, or the config file in the config directory.
+pub(crate) fn default_config_path() -> PathBuf {
+    if let Ok(p) = env::var("config_path") {
+        PathBuf::from(p)
+    } else {
+        find_config_file(&config_dir())
     }
 }
 
@@ -238,11 +576,80 @@ fn config_dir() -> PathBuf {
     }
 }
 
+/// sets the process-wide environment variable for the test and restores it on drop.
+/// The guard holds the shared lock, so the tests reading the variable are serialized.
+#[cfg(test)]
+pub(crate) struct EnvGuard {
+    name: String,
+    old: Option<std::ffi::OsString>,
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl EnvGuard {
+    pub(crate) fn set(name: &str, value: impl AsRef<std::ffi::OsStr>) -> Self {
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let old = env::var_os(name);
+        env::set_var(name, value);
+        Self { name: name.to_string(), old, _lock: lock }
+    }
+}
+
+#[cfg(test)]
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        match &self.old {
+            Some(v) => env::set_var(&self.name, v),
+            None => env::remove_var(&self.name),
+        }
+    }
+}
+
+/// creates the temporary directory (`rrh2-<name>-<pid>`) for the test and removes it on drop.
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("rrh2-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// bootstraps the default config and database in the directory, and returns the path of the config.
+    pub(crate) fn bootstrap(&self) -> PathBuf {
+        let config_path = self.0.join("config.json");
+        bootstrap(&Config::default_at(config_path.clone())).unwrap();
+        config_path
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::Local;
 
     use super::*;
+    use crate::db::RefDB;
+    use crate::entities::Group;
     use std::time::{Duration, SystemTime};
 
     #[test]
@@ -258,6 +665,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_store_after_other_update() {
+        let dir = TempDir::new("store");
+        let config_path = dir.bootstrap();
+        let mut first = Context::new_with_path(config_path.clone()).unwrap();
+        let mut second = Context::new_with_path(config_path.clone()).unwrap();
+        first.db.register_group(Group::new("first".into())).unwrap();
+        first.store().unwrap();
+        second.db.register_group(Group::new("second".into())).unwrap();
+        assert!(matches!(second.store(), Err(RrhError::DatabaseModified(_))));
+
+        first.db.register_group(Group::new("again".into())).unwrap();
+        first.store().unwrap();
+        second.update(|c| c.db.register_group(Group::new("second".into()))).unwrap();
+        let names = Context::new_with_path(config_path).unwrap().db.groups().unwrap()
+            .into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert!(["first", "again", "second"].iter().all(|n| names.contains(&n.to_string())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_pkl_config() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("pkl-store");
+        let evaluator = dir.join("pkl");
+        let json = format!("{{\"home\": \"{0}\", \"database_path\": \"{0}/database.json\", \"envs\": {{}}, \"aliases\": {{}}}}", dir.display());
+        std::fs::write(&evaluator, format!("#!/bin/sh\necho '{}'\n", json)).unwrap();
+        std::fs::set_permissions(&evaluator, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let pkl = dir.join("config.pkl");
+        std::fs::write(&pkl, "home = read(\"env:HOME\")\n").unwrap();
+        let _env = EnvGuard::set("RRH_PKL", &evaluator);
+
+        let mut c = load_config(find_config_file(&dir.to_path_buf())).unwrap();
+        assert_eq!(c.from, Some(pkl.clone()));
+        c.envs.insert("default_group".into(), EnvValue::of("work"));
+        assert!(matches!(store_config(&c), Err(RrhError::ConfigReadOnly(p)) if p == pkl));
+        assert_eq!(std::fs::read_to_string(&pkl).unwrap(), "home = read(\"env:HOME\")\n");
+        assert!(!dir.join("config.json").exists());
+    }
+
+    #[test]
+    fn test_load_shared_layer() {
+        let mut c = Config::new_with_path("testdata/config.json".into()).unwrap();
+        assert!(load_shared_layer(&c).is_none());
+        c.envs.insert("shared_database".into(), EnvValue::of("testdata"));
+        c.envs.insert("workspace_root".into(), EnvValue::of("/workspace"));
+        let db = load_shared_layer(&c).unwrap().unwrap();
+        assert_eq!(db.find_repository("fibonacci").unwrap().path, PathBuf::from("/workspace/testdata/fibonacci"));
+    }
+
+    #[test]
+    fn test_resolve_and_collapse_path() {
+        let mut c = Config::default_at(PathBuf::from("config.json"));
+        c.roots.insert("WORK".into(), "/work".into());
+        c.roots.insert("PROJECTS".into(), "/work/projects".into());
+        assert_eq!(c.resolve_path(Path::new("${WORK}/rrh")), PathBuf::from("/work/rrh"));
+        assert_eq!(c.resolve_path(Path::new("${WORK}")), PathBuf::from("/work"));
+        assert_eq!(c.resolve_path(Path::new("${UNKNOWN_ROOT_OF_RRH}/rrh")), PathBuf::from("${UNKNOWN_ROOT_OF_RRH}/rrh"));
+        assert_eq!(c.collapse_path(Path::new("/work/rrh")), PathBuf::from("${WORK}/rrh"));
+        assert_eq!(c.collapse_path(Path::new("/work/projects/rrh")), PathBuf::from("${PROJECTS}/rrh"));
+        assert_eq!(c.collapse_path(Path::new("/work")), PathBuf::from("${WORK}"));
+        assert_eq!(c.collapse_path(Path::new("/opt/rrh")), PathBuf::from("/opt/rrh"));
+        assert_eq!(c.collapse_path(Path::new("testdata/rrh")), PathBuf::from("testdata/rrh"));
+        if let Some(home) = dirs::home_dir() {
+            let p = home.join("src/rrh");
+            assert_eq!(c.collapse_path(&p), PathBuf::from("${HOME}/src/rrh"));
+            assert_eq!(c.resolve_path(&c.collapse_path(&p)), p);
+        }
+    }
+
     #[test]
     fn test_systemtime_to_string() {
         assert_eq!(Some(String::from("now")), format_time(SystemTime::now(), None));
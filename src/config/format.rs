@@ -0,0 +1,194 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::cli::{Result, RrhError};
+use crate::config::Config;
+use crate::logger;
+
+/// the file format of the configuration, decided by the extension of the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+    Pkl,
+}
+
+impl ConfigFormat {
+    pub(crate) fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()) {
+            Some(e) if e == "toml" => ConfigFormat::Toml,
+            Some(e) if e == "yaml" || e == "yml" => ConfigFormat::Yaml,
+            Some(e) if e == "pkl" => ConfigFormat::Pkl,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// the file names searched in the config directory, in the order of priority.
+    pub(crate) fn candidates() -> Vec<&'static str> {
+        vec!["config.json", "config.toml", "config.yaml", "config.yml", "config.pkl"]
+    }
+}
+
+pub(crate) fn parse(path: &Path) -> Result<Config> {
+    let format = ConfigFormat::of(path);
+    let data = match format {
+        ConfigFormat::Pkl => evaluate_pkl(path),
+        _ => std::fs::read_to_string(path).map_err(RrhError::IO),
+    }?;
+    let config: Result<Config> = match format {
+        ConfigFormat::Json | ConfigFormat::Pkl => serde_json::from_str(&data).map_err(RrhError::Json),
+        ConfigFormat::Toml => toml::from_str(&data).map_err(|e| RrhError::Toml(e.to_string())),
+        ConfigFormat::Yaml => serde_yaml::from_str(&data).map_err(RrhError::Yaml),
+    };
+    config.map(|mut c| {
+        c.format = format;
+        c
+    })
+}
+
+/// checks the config can be written into the given path.
+/// An existing Pkl file is never rewritten, since its expressions (e.g., `read("env:HOME")`) would be
+/// replaced with their evaluated values.
+pub(crate) fn check_writable(path: &Path, format: ConfigFormat) -> Result<()> {
+    match format {
+        ConfigFormat::Pkl if path.exists() => Err(RrhError::ConfigReadOnly(path.to_path_buf())),
+        _ => Ok(()),
+    }
+}
+
+/// serializes the config in the given format.
+pub(crate) fn serialize(config: &Config, format: ConfigFormat) -> Result<String> {
+    match format {
+        ConfigFormat::Json => serde_json::to_string(config).map_err(RrhError::Json),
+        ConfigFormat::Toml => toml::to_string(config).map_err(|e| RrhError::Toml(e.to_string())),
+        ConfigFormat::Yaml => serde_yaml::to_string(config).map_err(RrhError::Yaml),
+        ConfigFormat::Pkl => serde_json::to_value(config)
+            .map(|v| to_pkl(&v))
+            .map_err(RrhError::Json),
+    }
+}
+
+/// evaluates the Pkl file by the `pkl` command (or the command in `RRH_PKL`) into JSON.
+fn evaluate_pkl(path: &Path) -> Result<String> {
+    let evaluator = env::var("RRH_PKL").unwrap_or(String::from("pkl"));
+    let label = format!("{} eval -f json {}", evaluator, path.display());
+    let output = logger::timed(&label, || Command::new(&evaluator).args(["eval", "-f", "json"]).arg(path).output());
+    match output {
+        Ok(o) if o.status.success() => Ok(String::from_utf8_lossy(&o.stdout).to_string()),
+        Ok(o) => Err(RrhError::ExternalCommand(o.status, label)),
+        Err(e) => Err(RrhError::IO(e)),
+    }
+}
+
+fn to_pkl(v: &Value) -> String {
+    match v {
+        Value::Object(map) => map.iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| format!("{}{}\n", pkl_key(key), pkl_member(value, 0)))
+            .collect(),
+        _ => pkl_value(v, 0),
+    }
+}
+
+fn pkl_member(v: &Value, depth: usize) -> String {
+    match v {
+        Value::Object(_) => format!(" {}", pkl_value(v, depth)),
+        _ => format!(" = {}", pkl_value(v, depth)),
+    }
+}
+
+fn pkl_value(v: &Value, depth: usize) -> String {
+    let indent = "    ".repeat(depth);
+    match v {
+        Value::Null => String::from("null"),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => serde_json::to_string(s).unwrap_or_default(),
+        Value::Array(items) => format!(
+            "List({})",
+            items.iter().map(|i| pkl_element(i, depth)).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(map) => {
+            let members = map.iter()
+                .map(|(key, value)| format!("{}    {}{}\n", indent, pkl_key(key), pkl_member(value, depth + 1)))
+                .collect::<String>();
+            format!("{{\n{}{}}}", members, indent)
+        }
+    }
+}
+
+fn pkl_element(v: &Value, depth: usize) -> String {
+    match v {
+        Value::Object(_) => format!("new {}", pkl_value(v, depth)),
+        _ => pkl_value(v, depth),
+    }
+}
+
+fn pkl_key(key: &str) -> String {
+    let is_identifier = key.chars().next().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false)
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_identifier {
+        key.to_string()
+    } else {
+        format!("[{}]", serde_json::to_string(key).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::TempDir;
+
+    #[test]
+    fn test_format_of() {
+        assert_eq!(ConfigFormat::of(Path::new("config.json")), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::of(Path::new("config.TOML")), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::of(Path::new("config.yml")), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::of(Path::new("config.pkl")), ConfigFormat::Pkl);
+    }
+
+    #[test]
+    fn test_parse_toml_and_yaml() {
+        for path in ["testdata/config.toml", "testdata/config.yaml"] {
+            let c = parse(Path::new(path)).unwrap();
+            assert_eq!(c.database_path, PathBuf::from("testdata/database.json"));
+            assert_eq!(c.envs.len(), 8);
+            assert_eq!(c.aliases.len(), 2);
+            assert_eq!(c.format, ConfigFormat::of(Path::new(path)));
+        }
+    }
+
+    #[test]
+    fn test_serialize_toml() {
+        let c = parse(Path::new("testdata/config.toml")).unwrap();
+        let data = serialize(&c, c.format).unwrap();
+        let c2: Config = toml::from_str(&data).unwrap();
+        assert_eq!(c2.envs.len(), 8);
+        assert_eq!(c2.aliases.get("grlist"), Some(&vec![String::from("group"), String::from("list")]));
+    }
+
+    #[test]
+    fn test_to_pkl() {
+        let v = serde_json::json!({"database_path": "db.json", "envs": {"auto_create_group": true}, "aliases": {"gr-list": ["group", "list"]}});
+        assert_eq!(to_pkl(&v), "aliases {\n    [\"gr-list\"] = List(\"group\", \"list\")\n}\ndatabase_path = \"db.json\"\nenvs {\n    auto_create_group = true\n}\n");
+        let v = serde_json::json!({"items": [{"name": "a"}, 1]});
+        assert_eq!(to_pkl(&v), "items = List(new {\n    name = \"a\"\n}, 1)\n");
+    }
+
+    #[test]
+    fn test_check_writable() {
+        assert!(check_writable(Path::new("testdata/config.toml"), ConfigFormat::Toml).is_ok());
+        assert!(check_writable(Path::new("testdata/none.pkl"), ConfigFormat::Pkl).is_ok());
+        let dir = TempDir::new("pkl");
+        let path = dir.join("config.pkl");
+        std::fs::write(&path, "database_path = read(\"env:HOME\")\n").unwrap();
+        assert!(matches!(check_writable(&path, ConfigFormat::Pkl), Err(RrhError::ConfigReadOnly(p)) if p == path));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::cli::{Result, RrhError};
+use crate::config::EnvValue;
+
+/// the type of the value of the known config keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    Bool,
+    Integer,
+    String,
+    Choice(&'static [&'static str]),
+    TimeFormat,
+}
+
+/// the known keys of `Config.envs`.
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigKey {
+    pub(crate) name: &'static str,
+    pub(crate) value_type: ValueType,
+    pub(crate) default: &'static str,
+    pub(crate) description: &'static str,
+}
+
+pub(crate) const LIST_STYLES: &[&str] = &[
+    "psql", "ascii", "ascii_rounded", "empty", "blank", "markdown", "sharp", "rounded",
+    "modern_rounded", "re_structured_text", "dots", "modern", "extended", "csv",
+];
+
+pub(crate) const KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "auto_create_group",
+        value_type: ValueType::Bool,
+        default: "false",
+        description: "create the group if the repository is related to the non-existing group",
+    },
+    ConfigKey {
+        name: "auto_delete_group",
+        value_type: ValueType::Bool,
+        default: "false",
+        description: "delete the group when it becomes empty by removing repositories",
+    },
+    ConfigKey {
+        name: "clone_directory",
+        value_type: ValueType::String,
+        default: ".",
+        description: "the directory for the cloned repositories without the output directory",
+    },
+    ConfigKey {
+        name: "default_group",
+        value_type: ValueType::String,
+        default: "no-group",
+        description: "the group for the repositories registered without any groups",
+    },
+    ConfigKey {
+        name: "last_access_format",
+        value_type: ValueType::TimeFormat,
+        default: "humanize",
+        description: "the format of last access: relative, humanize, iso8601, rfc3339, rfc2822 or strftime(<format>)",
+    },
+    ConfigKey {
+        name: "last_access_reload_duration_secs",
+        value_type: ValueType::Integer,
+        default: "86400",
+        description: "the seconds for reloading the last access time of the repositories",
+    },
+    ConfigKey {
+        name: "print_list_style",
+        value_type: ValueType::Choice(LIST_STYLES),
+        default: "blank",
+        description: "the table style of the list commands",
+    },
+    ConfigKey {
+        name: "shared_database",
+        value_type: ValueType::String,
+        default: "",
+        description: "the read-only shared database, the file or the directory (e.g., git checkout) containing database.json",
+    },
+    ConfigKey {
+        name: "sort_on_update",
+        value_type: ValueType::Bool,
+        default: "false",
+        description: "sort the repositories and the groups in the database on storing",
+    },
+    ConfigKey {
+        name: "workspace_root",
+        value_type: ValueType::String,
+        default: "~",
+        description: "the directory resolving the relative paths of the repositories in the shared database",
+    },
+];
+
+pub(crate) fn find_key(name: &str) -> Option<&'static ConfigKey> {
+    KEYS.iter().find(|k| k.name == name)
+}
+
+impl ConfigKey {
+    pub(crate) fn default_value(&self) -> EnvValue {
+        self.parse(self.default)
+            .unwrap_or(EnvValue::of(self.default))
+    }
+
+    /// parses the given string into the EnvValue of the type of this key.
+    pub(crate) fn parse(&self, value: &str) -> Result<EnvValue> {
+        let invalid = |expected: &str| {
+            Err(RrhError::CliOptsInvalid(
+                "config".into(),
+                format!("{}: invalid value for {} (expected {})", value, self.name, expected),
+            ))
+        };
+        match &self.value_type {
+            ValueType::Bool => match parse_bool(value) {
+                Some(b) => Ok(EnvValue::Bool(b)),
+                None => invalid("true or false"),
+            },
+            ValueType::Integer => match value.parse::<i32>() {
+                Ok(i) => Ok(EnvValue::Value(i)),
+                Err(_) => invalid("integer"),
+            },
+            ValueType::String => Ok(EnvValue::of(value)),
+            ValueType::Choice(choices) => {
+                let v = value.to_lowercase();
+                if choices.contains(&v.as_str()) {
+                    Ok(EnvValue::Var(v))
+                } else {
+                    invalid(&choices.join(", "))
+                }
+            }
+            ValueType::TimeFormat => {
+                let v = value.to_lowercase();
+                let known = ["relative", "humanize", "iso", "iso8601", "rfc2822", "rfc3339"];
+                if known.contains(&v.as_str()) || (v.starts_with("strftime(") && v.ends_with(')')) {
+                    Ok(EnvValue::of(value))
+                } else {
+                    invalid("relative, humanize, iso8601, rfc3339, rfc2822 or strftime(<format>)")
+                }
+            }
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let key = find_key("auto_create_group").unwrap();
+        assert!(matches!(key.parse("yes"), Ok(EnvValue::Bool(true))));
+        assert!(key.parse("maybe").is_err());
+
+        let key = find_key("last_access_reload_duration_secs").unwrap();
+        assert!(matches!(key.parse("3600"), Ok(EnvValue::Value(3600))));
+        assert!(key.parse("an hour").is_err());
+
+        let key = find_key("print_list_style").unwrap();
+        assert!(matches!(key.parse("Markdown"), Ok(EnvValue::Var(s)) if s == "markdown"));
+        assert!(key.parse("fancy").is_err());
+
+        let key = find_key("last_access_format").unwrap();
+        assert!(key.parse("strftime(%Y-%m-%d)").is_ok());
+        assert!(key.parse("unix").is_err());
+    }
+}
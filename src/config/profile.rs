@@ -0,0 +1,104 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::cli::{Result, RrhError};
+use crate::config::{config_dir, default_config_path, find_config_file};
+
+/// the name of the profile pointing the config file in the config directory.
+pub(crate) const DEFAULT_PROFILE: &str = "default";
+
+/// the directory containing the profiles, each profile is a directory with its own config file.
+pub(crate) fn profiles_dir() -> PathBuf {
+    config_dir().join("profiles")
+}
+
+fn current_file() -> PathBuf {
+    config_dir().join("current_profile")
+}
+
+pub(crate) fn config_path_of(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        default_config_path()
+    } else {
+        find_config_file(&profiles_dir().join(name))
+    }
+}
+
+pub(crate) fn exists(name: &str) -> bool {
+    name == DEFAULT_PROFILE || config_path_of(name).exists()
+}
+
+/// returns the names of the profiles, except the default profile.
+pub(crate) fn names() -> Vec<String> {
+    let mut result = match std::fs::read_dir(profiles_dir()) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+            .filter(|name| exists(name))
+            .collect::<Vec<_>>(),
+        Err(_) => vec![],
+    };
+    result.sort();
+    result
+}
+
+/// returns the profile chosen by `rrh profile use`.
+pub(crate) fn current() -> Option<String> {
+    std::fs::read_to_string(current_file())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+pub(crate) fn set_current(name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        match std::fs::remove_file(current_file()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(RrhError::IO(e)),
+            _ => Ok(()),
+        }
+    } else {
+        std::fs::write(current_file(), name).map_err(RrhError::IO)
+    }
+}
+
+/// decides the profile in the order of the given name (`--profile`), `RRH_PROFILE` env,
+/// and the current profile.
+pub(crate) fn selected(name: Option<String>) -> String {
+    name.or_else(|| env::var("RRH_PROFILE").ok().filter(|s| !s.is_empty()))
+        .or_else(current)
+        .unwrap_or(String::from(DEFAULT_PROFILE))
+}
+
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(RrhError::CliOptsInvalid(
+            "profile".into(),
+            format!("{}: invalid profile name", name),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("team-2.0").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../work").is_err());
+        assert!(validate_name(".hidden").is_err());
+    }
+
+    #[test]
+    fn test_selected() {
+        assert_eq!(selected(Some(String::from("work"))), "work");
+    }
+}
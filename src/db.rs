@@ -1,9 +1,12 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use crate::entities::{Group, Relation, Repository, RepositoryWithGroups};
-use crate::cli::Result;
+use crate::cli::{Result, RrhError};
+use crate::selector::{self, RuleCache};
 
 pub mod jsondb;
+pub mod layered;
 
 pub trait RefDB {
     /// find a repository by its id
@@ -22,13 +25,134 @@ pub trait RefDB {
     fn find_relation(&self, repo_id: &str, group_name: &str) -> Option<Relation>;
     /// find relations by repository id.
     fn find_relation_with_repository(&self, repo_id: &str) -> Vec<Relation>;
-    /// find relations by group name.
+    /// find relations by group name in the order of their positions.
     fn find_relation_with_group(&self, group_name: &str) -> Vec<Relation>;
     /// find all groups.
     fn groups(&self) -> Result<Vec<Group>>;
     /// find all repositories. the key of the resultant map is the group name.
     fn group_repositories(&self) -> Result<HashMap<String, Vec<Repository>>>;
     fn repositories(&self) -> Result<Vec<Repository>>;
+    /// the cache of the smart group rules, which must be cleared on every update of the database.
+    fn rule_cache(&self) -> Option<&RuleCache> {
+        None
+    }
+
+    /// find the child groups of a given group name.
+    fn find_children(&self, group_name: &str) -> Result<Vec<Group>> {
+        match self.groups() {
+            Ok(groups) => Ok(groups.into_iter()
+                .filter(|g| g.parent.as_deref() == Some(group_name))
+                .collect()),
+            Err(e) => Err(e),
+        }
+    }
+    /// find the descendant groups of a given group name in the depth-first order.
+    fn find_descendants(&self, group_name: &str) -> Result<Vec<Group>> {
+        let mut result: Vec<Group> = vec![];
+        let mut stack = vec![group_name.to_string()];
+        while let Some(name) = stack.pop() {
+            let mut children = match self.find_children(&name) {
+                Ok(children) => children,
+                Err(e) => return Err(e),
+            };
+            children.retain(|c| c.name != group_name && !result.iter().any(|g| g.name == c.name));
+            stack.extend(children.iter().rev().map(|c| c.name.clone()));
+            result.extend(children);
+        }
+        Ok(result)
+    }
+    /// find the ancestor groups of a given group name from its parent to the root.
+    fn find_ancestors(&self, group_name: &str) -> Vec<Group> {
+        let mut result: Vec<Group> = vec![];
+        let mut current = self.find_group(group_name).and_then(|g| g.parent);
+        while let Some(name) = current {
+            if name == group_name || result.iter().any(|g| g.name == name) {
+                break;
+            }
+            match self.find_group(&name) {
+                Some(g) => {
+                    current = g.parent.clone();
+                    result.push(g);
+                }
+                None => break,
+            }
+        }
+        result
+    }
+    /// find repositories related with a given group name and its descendants without duplicates.
+    fn find_repositories_under(&self, group_name: &str) -> Result<Vec<Repository>> {
+        let mut names = vec![group_name.to_string()];
+        match self.find_descendants(group_name) {
+            Ok(groups) => names.extend(groups.into_iter().map(|g| g.name)),
+            Err(e) => return Err(e),
+        }
+        let mut result: Vec<Repository> = vec![];
+        for name in names {
+            let repos = match self.find_repositories_of(&name) {
+                Ok(repos) => repos,
+                Err(e) => return Err(e),
+            };
+            for repo in repos {
+                if !result.iter().any(|r| r.id == repo.id) {
+                    result.push(repo);
+                }
+            }
+        }
+        Ok(result)
+    }
+    /// returns the slash-separated path of a given group name from the root (e.g., `company/team`).
+    fn group_path(&self, group_name: &str) -> String {
+        let mut names = self.find_ancestors(group_name).into_iter().map(|g| g.name).collect::<Vec<_>>();
+        names.reverse();
+        names.push(group_name.to_string());
+        names.join("/")
+    }
+    /// checks that a given group can be moved under the new parent (not itself nor its descendants).
+    fn check_parent(&self, group_name: &str, parent: &str) -> Result<()> {
+        if self.find_group(parent).is_none() {
+            return Err(RrhError::GroupNotFound(parent.to_string()));
+        }
+        if parent == group_name || self.find_ancestors(parent).iter().any(|g| g.name == group_name) {
+            return Err(RrhError::GroupCycle(group_name.to_string(), parent.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// appends the repositories matched with the rule of the smart group to the related repositories.
+/// The rule may refer to the other smart groups, and the cycles of the rules are not followed.
+/// The broken rule is warned and matches nothing, as `with_rule_groups` does.
+pub(crate) fn with_rule_members(db: &dyn RefDB, group_name: &str, mut repos: Vec<Repository>) -> Result<Vec<Repository>> {
+    let group = match db.find_group(group_name) {
+        Some(g) if g.is_smart() => g,
+        _ => return Ok(repos),
+    };
+    let all = match db.repositories() {
+        Ok(all) => all,
+        Err(e) => return Err(e),
+    };
+    for repo in all {
+        if !repos.iter().any(|r| r.id == repo.id) && selector::rule_matches(db, &group, &repo, &mut vec![]) {
+            repos.push(repo);
+        }
+    }
+    Ok(repos)
+}
+
+/// appends the smart groups whose rules match the repository to the related groups.
+/// The broken rules are warned and skipped, so that the repository is still shown.
+pub(crate) fn with_rule_groups(db: &dyn RefDB, id: &str, mut groups: Vec<Group>) -> Vec<Group> {
+    let repo = match db.find_repository(id) {
+        Some(r) => r,
+        None => return groups,
+    };
+    for group in db.groups().unwrap_or_default() {
+        if group.is_smart() && !groups.iter().any(|g| g.name == group.name)
+                && selector::rule_matches(db, &group, &repo, &mut vec![]) {
+            groups.push(group);
+        }
+    }
+    groups
 }
 
 pub trait Database: RefDB {
@@ -37,9 +161,15 @@ pub trait Database: RefDB {
     fn update_group(&mut self, name: String, group: Group) -> Result<()>;
     fn update_repository(&mut self, id: String, r: Repository) -> Result<()>;
     fn relate(&mut self, id: String, group_name: String) -> Result<Relation>;
+    /// update the position of the existing relation.
+    fn update_relation(&mut self, r: Relation) -> Result<()>;
     fn delete_relation(&mut self, id: String, group_name: String) -> Result<()>;
     fn delete_repository(&mut self, id: String) -> Result<()>;
     fn delete_group(&mut self, group_name: String) -> Result<()>;
+    /// sort the repositories, groups and relations by their names (and the positions of the relations).
+    fn sort(&mut self);
+    /// rewrite the paths of the repositories by the given function.
+    fn map_paths(&mut self, f: &dyn Fn(&Path) -> PathBuf);
     fn store(&mut self, out: Box<dyn std::io::Write>) -> Result<()>;
 }
 
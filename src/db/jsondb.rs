@@ -6,16 +6,19 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::cli::{Result, RrhError};
-use crate::db::{Database, RefDB};
+use crate::db::{with_rule_groups, with_rule_members, Database, RefDB};
 use crate::entities::{Group, Relation, Repository, RepositoryWithGroups};
+use crate::selector::RuleCache;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct JsonDB {
     last_modified: chrono::DateTime<chrono::Utc>,
     repositories: Vec<Repository>,
     groups: Vec<Group>,
     relations: Vec<Relation>,
+    #[serde(skip)]
+    rule_cache: RuleCache,
 }
 
 impl JsonDB {
@@ -26,7 +29,23 @@ impl JsonDB {
         }
     }
 
-    fn from_str(data: &str) -> Result<Self> {
+    /// copies the repositories, groups, and relations (in the order of their positions) of the given database.
+    pub fn from_db(db: &dyn RefDB) -> Result<Self> {
+        let repositories = match db.repositories() {
+            Ok(repos) => repos,
+            Err(e) => return Err(e),
+        };
+        let groups = match db.groups() {
+            Ok(groups) => groups,
+            Err(e) => return Err(e),
+        };
+        let relations = groups.iter()
+            .flat_map(|g| db.find_relation_with_group(&g.name))
+            .collect();
+        Ok(Self { last_modified: chrono::Utc::now(), repositories, groups, relations, rule_cache: RuleCache::default() })
+    }
+
+    pub(crate) fn from_str(data: &str) -> Result<Self> {
         match serde_json::from_str(data) {
             Ok(mut db) => {
                 update_recent(&mut db);
@@ -36,6 +55,11 @@ impl JsonDB {
         }
     }
 
+    /// resolves the relative paths of the repositories against the given root directory.
+    pub fn rebase(&mut self, root: &Path) {
+        self.map_paths(&|p| if p.is_relative() { root.join(p) } else { p.to_path_buf() });
+    }
+
     pub fn to_json(&mut self) -> Result<String> {
         self.last_modified = chrono::Utc::now();
         match serde_json::to_string(self) {
@@ -43,9 +67,21 @@ impl JsonDB {
             Err(e) => Err(RrhError::Json(e)),
         }
     }
+
+    pub fn to_pretty_json(&mut self) -> Result<String> {
+        self.last_modified = chrono::Utc::now();
+        match serde_json::to_string_pretty(self) {
+            Ok(data) => Ok(data),
+            Err(e) => Err(RrhError::Json(e)),
+        }
+    }
 }
 
 impl RefDB for JsonDB {
+    fn rule_cache(&self) -> Option<&RuleCache> {
+        Some(&self.rule_cache)
+    }
+
     fn find_repository(&self, id: &str) -> Option<Repository> {
         self.repositories.iter().find(|r| r.id == id).cloned()
     }
@@ -76,15 +112,13 @@ impl RefDB for JsonDB {
                 groups.push(g);
             }
         }
-        Ok(groups)
+        Ok(with_rule_groups(self, id, groups))
     }
 
     fn find_repositories_of(&self, group_name: &str) -> Result<Vec<Repository>> {
-        let repo_ids = self
-            .relations
-            .iter()
-            .filter(|r| r.group == group_name)
-            .map(|r| r.id.clone())
+        let repo_ids = self.find_relation_with_group(group_name)
+            .into_iter()
+            .map(|r| r.id)
             .collect::<Vec<_>>();
         let mut repositories = Vec::new();
         for id in repo_ids {
@@ -92,7 +126,7 @@ impl RefDB for JsonDB {
                 repositories.push(r);
             }
         }
-        Ok(repositories)
+        with_rule_members(self, group_name, repositories)
     }
 
     fn has_relation(&self, repo_id: &str, group_name: &str) -> bool {
@@ -112,6 +146,7 @@ impl RefDB for JsonDB {
         self.relations.iter()
             .filter(|r| r.group == group_name)
             .map(|r| r.clone())
+            .sorted_by_key(|r| r.position_key())
             .collect::<Vec<Relation>>()
     }
 
@@ -149,29 +184,28 @@ impl RefDB for JsonDB {
 
 impl Database for JsonDB {
     fn register(&mut self, r: Repository, group_names: Vec<String>) -> Result<()> {
+        self.rule_cache.clear();
         if let Some(_) = self.find_repository(&r.id) {
             return Err(RrhError::RepositoryExists(r.id.clone()));
         }
-        for name in group_names.clone() {
-            if !self.find_group(&name).is_none() {
-                self.register_group(Group::new(name.clone()))?;
-            }
+        let errs = group_names.iter()
+            .filter(|name| self.find_group(name).is_none())
+            .map(|name| RrhError::GroupNotFound(name.clone()))
+            .collect::<Vec<_>>();
+        if errs.len() > 0 {
+            return Err(RrhError::Arrays(errs));
         }
-        let mut errs = Vec::new();
         for name in group_names {
-            if let Err(e) = self.relate(r.id.clone(), name.clone()) {
-                errs.push(e);
+            if let Err(e) = self.relate(r.id.clone(), name) {
+                return Err(e);
             }
         }
         self.repositories.push(r);
-        if errs.len() > 0 {
-            Err(RrhError::Arrays(errs))
-        } else {
-            Ok(())
-        }
+        Ok(())
     }
 
     fn register_group(&mut self, g: Group) -> Result<()> {
+        self.rule_cache.clear();
         if let Some(_) = self.find_group(&g.name) {
             return Err(RrhError::GroupExists(g.name.clone()));
         }
@@ -180,6 +214,7 @@ impl Database for JsonDB {
     }
 
     fn update_group(&mut self, name: String, group: Group) -> Result<()> {
+        self.rule_cache.clear();
         let old_name = name.clone();
         let new_name = group.name.clone();
         let r = self.groups
@@ -195,6 +230,7 @@ impl Database for JsonDB {
     }
 
     fn update_repository(&mut self, id: String, r: Repository) -> Result<()> {
+        self.rule_cache.clear();
         let new_name = r.id.clone();
         let old_name = id.clone();
         let r = self.repositories
@@ -209,6 +245,7 @@ impl Database for JsonDB {
     }
 
     fn relate(&mut self, id: String, group_name: String) -> Result<Relation> {
+        self.rule_cache.clear();
         match self.find_relation(&id, &group_name) {
             Some(relation) => Ok(relation),
             None => {
@@ -219,7 +256,19 @@ impl Database for JsonDB {
         }
     }
 
+    fn update_relation(&mut self, r: Relation) -> Result<()> {
+        self.rule_cache.clear();
+        match self.relations.iter_mut().find(|rel| rel.id == r.id && rel.group == r.group) {
+            Some(rel) => {
+                rel.position = r.position;
+                Ok(())
+            }
+            None => Err(RrhError::RelationNotFound(r.id, r.group)),
+        }
+    }
+
     fn delete_relation(&mut self, id: String, group_name: String) -> Result<()> {
+        self.rule_cache.clear();
         let idx = self
             .relations
             .iter()
@@ -234,6 +283,7 @@ impl Database for JsonDB {
     }
 
     fn delete_repository(&mut self, id: String) -> Result<()> {
+        self.rule_cache.clear();
         match self.repositories.iter().position(|r| r.id == id) {
             Some(i) => {
                 self.repositories.remove(i);
@@ -243,17 +293,40 @@ impl Database for JsonDB {
         }
     }
 
+    /// the child groups of the deleted group are moved to its parent.
     fn delete_group(&mut self, group_name: String) -> Result<()> {
+        self.rule_cache.clear();
         let idx = self.groups.iter().position(|g| g.name == group_name);
         match idx {
             Some(i) => {
-                self.groups.remove(i);
+                let removed = self.groups.remove(i);
+                self.groups.iter_mut()
+                    .filter(|g| g.parent.as_deref() == Some(group_name.as_str()))
+                    .for_each(|g| g.parent = removed.parent.clone());
                 delete_relation_all_for_group(self, group_name.clone())
             }
             None => Err(RrhError::GroupNotFound(group_name)),
         }
     }
 
+    /// the last access times are updated only if the new paths exist.
+    fn map_paths(&mut self, f: &dyn Fn(&Path) -> PathBuf) {
+        self.rule_cache.clear();
+        self.repositories.iter_mut()
+            .for_each(|r| {
+                r.path = f(&r.path);
+                if let Some(t) = find_last_access(&r.path) {
+                    r.last_access = Some(t);
+                }
+            });
+    }
+
+    fn sort(&mut self) {
+        self.repositories.sort_by(|a, b| a.id.cmp(&b.id));
+        self.groups.sort_by(|a, b| a.name.cmp(&b.name));
+        self.relations.sort_by(|a, b| (&a.group, a.position_key(), &a.id).cmp(&(&b.group, b.position_key(), &b.id)));
+    }
+
     fn store(&mut self, mut out: Box<dyn std::io::Write>) -> Result<()> {
         match self.to_json() {
             Ok(data) => match out.write(data.as_bytes()) {
@@ -272,10 +345,14 @@ fn update_relations_all_for_repository(db: &mut JsonDB, old_name: &str, new_name
     Ok(())
 }
 
+/// renames the group in the relations and the parents of the child groups.
 fn update_relations_all_for_group(db: &mut JsonDB, old_name: &str, new_name: &str) -> Result<()> {
     db.relations.iter_mut()
         .filter(|r| r.group == old_name)
         .for_each(|r| r.group = new_name.to_string());
+    db.groups.iter_mut()
+        .filter(|g| g.parent.as_deref() == Some(old_name))
+        .for_each(|g| g.parent = Some(new_name.to_string()));
     Ok(())
 }
 
@@ -343,6 +420,7 @@ pub(crate) fn find_orphan_repositories(db: &JsonDB) -> Vec<Repository> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::selector::Selector;
 
     #[test]
     fn test_load() {
@@ -385,4 +463,95 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_sort() {
+        let mut db = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        db.register_group(Group::new("a-group".into())).unwrap();
+        db.relate("helloworld".into(), "a-group".into()).unwrap();
+        db.sort();
+        assert_eq!("a-group", db.groups[0].name);
+        assert_eq!(Relation::new("helloworld".into(), "a-group".into()), db.relations[0]);
+    }
+
+    #[test]
+    fn test_group_tree() {
+        let mut db = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        for (name, parent) in [("company", None), ("team", Some("company")), ("service", Some("team"))] {
+            let mut g = Group::new(name.into());
+            g.parent = parent.map(String::from);
+            db.register_group(g).unwrap();
+        }
+        db.relate("helloworld".into(), "service".into()).unwrap();
+        db.relate("fibonacci".into(), "team".into()).unwrap();
+        assert_eq!(db.group_path("service"), "company/team/service");
+        let names = db.find_descendants("company").unwrap().into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["team", "service"]);
+        assert_eq!(db.find_repositories_under("company").unwrap().len(), 2);
+        assert!(matches!(db.check_parent("company", "service"), Err(RrhError::GroupCycle(_, _))));
+        assert!(db.check_parent("service", "company").is_ok());
+
+        let mut team = db.find_group("team").unwrap();
+        team.name = "squad".into();
+        db.update_group("team".into(), team).unwrap();
+        assert_eq!(db.group_path("service"), "company/squad/service");
+        db.delete_group("squad".into()).unwrap();
+        assert_eq!(db.find_group("service").unwrap().parent, Some(String::from("company")));
+    }
+
+    #[test]
+    fn test_smart_group() {
+        let mut db = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        let mut g = Group::new("hello".into());
+        g.rule = Some(String::from("id~^hello"));
+        db.register_group(g).unwrap();
+        db.relate("fibonacci".into(), "hello".into()).unwrap();
+        let ids = db.find_repositories_of("hello").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["fibonacci", "helloworld"]);
+        let names = db.find_groups_of("helloworld").unwrap().into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["no-group", "hello"]);
+        let ids = Selector::parse("group:hello and not fibonacci").unwrap().select(&db).unwrap()
+            .into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld"]);
+
+        for (name, rule) in [("other", "group:hello and id~world"), ("ping", "pong or id:fib*"), ("pong", "ping")] {
+            let mut g = Group::new(name.into());
+            g.rule = Some(String::from(rule));
+            db.register_group(g).unwrap();
+        }
+        let ids = db.find_repositories_of("other").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld"]);
+        let ids = db.find_repositories_of("pong").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["fibonacci"]);
+
+        let mut g = Group::new("broken".into());
+        g.rule = Some(String::from("unknown:x"));
+        db.register_group(g).unwrap();
+        assert!(db.find_repositories_of("broken").unwrap().is_empty());
+        assert!(db.group_repositories().is_ok());
+    }
+
+    #[test]
+    fn test_register_with_unknown_group() {
+        let mut db = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        let repo = Repository::new("newone".into(), PathBuf::from("newone"), None);
+        assert!(db.register(repo, vec!["no-group".into(), "unknown".into()]).is_err());
+        assert!(db.find_repository("newone").is_none());
+        assert!(db.find_relation_with_repository("newone").is_empty());
+    }
+
+    #[test]
+    fn test_relation_position() {
+        let mut db = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        let mut relation = db.find_relation("helloworld", "no-group").unwrap();
+        relation.position = Some(0);
+        db.update_relation(relation).unwrap();
+        db.sort();
+        let ids = db.find_repositories_of("no-group").unwrap().into_iter().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["helloworld", "fibonacci"]);
+        assert!(matches!(db.update_relation(Relation::new("unknown".into(), "no-group".into())), Err(RrhError::RelationNotFound(_, _))));
+
+        let db = JsonDB::from_str(&db.to_json().unwrap()).unwrap();
+        assert_eq!(db.find_relation_with_group("no-group")[0].position, Some(0));
+    }
 }
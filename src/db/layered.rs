@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Result, RrhError};
+use crate::db::{with_rule_groups, with_rule_members, Database, RefDB};
+use crate::entities::{Group, Relation, Repository, RepositoryWithGroups};
+use crate::selector::RuleCache;
+
+/// the read-only database stacked under the writable database.
+pub struct Layer {
+    pub name: String,
+    pub db: Box<dyn RefDB>,
+}
+
+impl Layer {
+    pub fn new(name: &str, db: Box<dyn RefDB>) -> Self {
+        Self { name: name.to_string(), db }
+    }
+}
+
+/// the database merging the writable database and the read-only layers at query time.
+/// The entries of the writable database take precedence over the ones of the layers,
+/// and the earlier layers take precedence over the later ones.
+/// All updates are applied to the writable database, and the updates of the entries only in
+/// the layers are rejected.
+pub struct LayeredDB {
+    base: Box<dyn Database>,
+    layers: Vec<Layer>,
+    rule_cache: RuleCache,
+}
+
+impl LayeredDB {
+    pub fn new(base: Box<dyn Database>, layers: Vec<Layer>) -> Self {
+        Self { base, layers, rule_cache: RuleCache::default() }
+    }
+
+    fn dbs(&self) -> Vec<&dyn RefDB> {
+        let mut result: Vec<&dyn RefDB> = vec![self.base.as_ref()];
+        result.extend(self.layers.iter().map(|l| l.db.as_ref()));
+        result
+    }
+
+    /// returns the pairs of the repository id and the layer name, which have the repository
+    /// of the same id and the different path from the writable database.
+    pub fn conflicts(&self) -> Vec<(String, String)> {
+        let repos = self.base.repositories().unwrap_or_default();
+        let mut result = vec![];
+        for repo in repos {
+            for layer in self.layers.iter() {
+                if let Some(other) = layer.db.find_repository(&repo.id) {
+                    if other.path != repo.path {
+                        result.push((repo.id.clone(), layer.name.clone()));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn layer_having<F>(&self, f: F) -> Option<&Layer>
+            where F: Fn(&dyn RefDB) -> bool {
+        self.layers.iter().find(|l| f(l.db.as_ref()))
+    }
+
+    fn reject_repository(&self, id: &str) -> Result<()> {
+        if self.base.find_repository(id).is_some() {
+            return Ok(());
+        }
+        match self.layer_having(|db| db.find_repository(id).is_some()) {
+            Some(layer) => Err(RrhError::ReadOnlyEntry(id.to_string(), layer.name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn reject_group(&self, name: &str) -> Result<()> {
+        if self.base.find_group(name).is_some() {
+            return Ok(());
+        }
+        match self.layer_having(|db| db.find_group(name).is_some()) {
+            Some(layer) => Err(RrhError::ReadOnlyEntry(name.to_string(), layer.name.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+fn merge<T, K, F>(lists: Vec<Vec<T>>, key: F) -> Vec<T>
+        where K: PartialEq, F: Fn(&T) -> K {
+    let mut result: Vec<T> = Vec::new();
+    for item in lists.into_iter().flatten() {
+        if !result.iter().any(|r| key(r) == key(&item)) {
+            result.push(item);
+        }
+    }
+    result
+}
+
+impl RefDB for LayeredDB {
+    fn rule_cache(&self) -> Option<&RuleCache> {
+        Some(&self.rule_cache)
+    }
+
+    fn find_repository(&self, id: &str) -> Option<Repository> {
+        self.dbs().into_iter().find_map(|db| db.find_repository(id))
+    }
+
+    fn find_repository_with_groups(&self, id: &str) -> Option<RepositoryWithGroups> {
+        if let Some(repo) = self.find_repository(id) {
+            if let Ok(groups) = self.find_groups_of(id) {
+                return Some(RepositoryWithGroups { repo, groups })
+            }
+        }
+        None
+    }
+
+    fn find_group(&self, name: &str) -> Option<Group> {
+        self.dbs().into_iter().find_map(|db| db.find_group(name))
+    }
+
+    fn find_groups_of(&self, id: &str) -> Result<Vec<Group>> {
+        let groups = self.find_relation_with_repository(id)
+            .iter()
+            .filter_map(|r| self.find_group(&r.group))
+            .collect();
+        Ok(with_rule_groups(self, id, groups))
+    }
+
+    fn find_repositories_of(&self, group_name: &str) -> Result<Vec<Repository>> {
+        let repos = self.find_relation_with_group(group_name)
+            .iter()
+            .filter_map(|r| self.find_repository(&r.id))
+            .collect();
+        with_rule_members(self, group_name, repos)
+    }
+
+    fn has_relation(&self, repo_id: &str, group_name: &str) -> bool {
+        self.dbs().iter().any(|db| db.has_relation(repo_id, group_name))
+    }
+
+    fn find_relation(&self, repo_id: &str, group_name: &str) -> Option<Relation> {
+        self.dbs().into_iter().find_map(|db| db.find_relation(repo_id, group_name))
+    }
+
+    fn find_relation_with_repository(&self, repo_id: &str) -> Vec<Relation> {
+        let lists = self.dbs().iter()
+            .map(|db| db.find_relation_with_repository(repo_id))
+            .collect();
+        merge(lists, |r| r.group.clone())
+    }
+
+    fn find_relation_with_group(&self, group_name: &str) -> Vec<Relation> {
+        let lists = self.dbs().iter()
+            .map(|db| db.find_relation_with_group(group_name))
+            .collect();
+        let mut result = merge(lists, |r| r.id.clone());
+        result.sort_by_key(|r| r.position_key());
+        result
+    }
+
+    fn groups(&self) -> Result<Vec<Group>> {
+        let mut lists = Vec::new();
+        for db in self.dbs() {
+            match db.groups() {
+                Ok(groups) => lists.push(groups),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(merge(lists, |g| g.name.clone()))
+    }
+
+    fn group_repositories(&self) -> Result<HashMap<String, Vec<Repository>>> {
+        let mut result = HashMap::new();
+        for group in self.groups()? {
+            match self.find_repositories_of(&group.name) {
+                Ok(repos) => _ = result.insert(group.name, repos),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(result)
+    }
+
+    fn repositories(&self) -> Result<Vec<Repository>> {
+        let mut lists = Vec::new();
+        for db in self.dbs() {
+            match db.repositories() {
+                Ok(repos) => lists.push(repos),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(merge(lists, |r| r.id.clone()))
+    }
+}
+
+impl Database for LayeredDB {
+    /// registers the repository into the writable database.
+    /// The groups only in the layers are related by the relations of the writable database.
+    fn register(&mut self, r: Repository, group_names: Vec<String>) -> Result<()> {
+        self.rule_cache.clear();
+        if self.find_repository(&r.id).is_some() {
+            return Err(RrhError::RepositoryExists(r.id));
+        }
+        let (shared, own): (Vec<String>, Vec<String>) = group_names.into_iter()
+            .partition(|name| self.base.find_group(name).is_none() && self.find_group(name).is_some());
+        let id = r.id.clone();
+        if let Err(e) = self.base.register(r, own) {
+            return Err(e);
+        }
+        for name in shared {
+            if let Err(e) = self.base.relate(id.clone(), name) {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn register_group(&mut self, g: Group) -> Result<()> {
+        self.rule_cache.clear();
+        if self.find_group(&g.name).is_some() {
+            return Err(RrhError::GroupExists(g.name));
+        }
+        self.base.register_group(g)
+    }
+
+    fn update_group(&mut self, name: String, group: Group) -> Result<()> {
+        self.rule_cache.clear();
+        if let Err(e) = self.reject_group(&name) {
+            return Err(e);
+        }
+        self.base.update_group(name, group)
+    }
+
+    fn update_repository(&mut self, id: String, r: Repository) -> Result<()> {
+        self.rule_cache.clear();
+        if let Err(e) = self.reject_repository(&id) {
+            return Err(e);
+        }
+        self.base.update_repository(id, r)
+    }
+
+    fn relate(&mut self, id: String, group_name: String) -> Result<Relation> {
+        self.rule_cache.clear();
+        self.base.relate(id, group_name)
+    }
+
+    /// the relations only in the layers are copied into the writable database to hold the position.
+    fn update_relation(&mut self, r: Relation) -> Result<()> {
+        self.rule_cache.clear();
+        if !self.base.has_relation(&r.id, &r.group) {
+            if !self.has_relation(&r.id, &r.group) {
+                return Err(RrhError::RelationNotFound(r.id, r.group));
+            }
+            if let Err(e) = self.base.relate(r.id.clone(), r.group.clone()) {
+                return Err(e);
+            }
+        }
+        self.base.update_relation(r)
+    }
+
+    fn delete_relation(&mut self, id: String, group_name: String) -> Result<()> {
+        self.rule_cache.clear();
+        if !self.base.has_relation(&id, &group_name) {
+            if let Some(layer) = self.layer_having(|db| db.has_relation(&id, &group_name)) {
+                return Err(RrhError::ReadOnlyEntry(format!("{}/{}", group_name, id), layer.name.clone()));
+            }
+        }
+        self.base.delete_relation(id, group_name)
+    }
+
+    fn delete_repository(&mut self, id: String) -> Result<()> {
+        self.rule_cache.clear();
+        if let Err(e) = self.reject_repository(&id) {
+            return Err(e);
+        }
+        self.base.delete_repository(id)
+    }
+
+    fn delete_group(&mut self, group_name: String) -> Result<()> {
+        self.rule_cache.clear();
+        if let Err(e) = self.reject_group(&group_name) {
+            return Err(e);
+        }
+        self.base.delete_group(group_name)
+    }
+
+    fn sort(&mut self) {
+        self.base.sort()
+    }
+
+    /// maps only the paths of the writable database, the layers are resolved on loading.
+    fn map_paths(&mut self, f: &dyn Fn(&Path) -> PathBuf) {
+        self.rule_cache.clear();
+        self.base.map_paths(f)
+    }
+
+    /// stores only the writable database.
+    fn store(&mut self, out: Box<dyn std::io::Write>) -> Result<()> {
+        self.base.store(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::jsondb::JsonDB;
+
+    use super::*;
+
+    fn layered() -> LayeredDB {
+        let mut personal = JsonDB::default();
+        personal.register_group(Group::new("mine".into())).unwrap();
+        personal.register(Repository::new("fibonacci".into(), PathBuf::from("personal/fibonacci"), None), vec!["mine".into()]).unwrap();
+        let team = JsonDB::load(PathBuf::from("testdata/database.json")).unwrap();
+        LayeredDB::new(Box::new(personal), vec![Layer::new("team", Box::new(team))])
+    }
+
+    #[test]
+    fn test_merged_queries() {
+        let db = layered();
+        assert_eq!(db.repositories().unwrap().len(), 2);
+        assert_eq!(db.groups().unwrap().len(), 2);
+        assert_eq!(db.find_repository("fibonacci").unwrap().path, PathBuf::from("personal/fibonacci"));
+        assert!(db.find_repository("helloworld").is_some());
+        let groups = db.find_groups_of("fibonacci").unwrap();
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn test_updates_go_to_base() {
+        let mut db = layered();
+        db.relate("helloworld".into(), "mine".into()).unwrap();
+        assert_eq!(db.find_repositories_of("mine").unwrap().len(), 2);
+        db.register(Repository::new("newone".into(), PathBuf::from("newone"), None), vec!["no-group".into()]).unwrap();
+        assert!(db.has_relation("newone", "no-group"));
+        assert!(matches!(db.register_group(Group::new("no-group".into())), Err(RrhError::GroupExists(_))));
+    }
+
+    #[test]
+    fn test_reject_shared_entries() {
+        let mut db = layered();
+        assert!(matches!(db.delete_repository("helloworld".into()), Err(RrhError::ReadOnlyEntry(_, l)) if l == "team"));
+        assert!(matches!(db.delete_group("no-group".into()), Err(RrhError::ReadOnlyEntry(_, _))));
+        assert!(matches!(db.delete_relation("helloworld".into(), "no-group".into()), Err(RrhError::ReadOnlyEntry(_, _))));
+        assert!(matches!(db.register(Repository::new("helloworld".into(), PathBuf::from("x"), None), vec![]), Err(RrhError::RepositoryExists(_))));
+        assert!(db.delete_repository("fibonacci".into()).is_ok());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let db = layered();
+        assert_eq!(db.conflicts(), vec![(String::from("fibonacci"), String::from("team"))]);
+    }
+}
@@ -1,21 +1,55 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 
-use crate::cli::RepositoryEntry;
+use crate::cli::{RepositoryEntry, Result, RrhError};
 use crate::config::{self, Config};
+use crate::db::RefDB;
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Group {
     pub name: String,
     pub note: String,
     pub abbrev: Option<bool>,
+    /// the name of the parent group, the groups without the parent are the roots of the tree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// the selector expression of the smart group, its matched repositories are the members
+    /// of the group in addition to the related repositories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// the directory for cloning the repositories into the group (`clone -g GROUP`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clone_dir: Option<String>,
+    /// the default command of `exec -g GROUP` without the commands, the shell script or the shell alias.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<String>,
+    /// the color of the group header in `list`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// the sort order of the group, the smaller comes first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
+    /// the hidden groups are excluded from the listings without `--all`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
 }
 
 impl Group {
     pub fn new_with(name: String, note: String, abbrev: Option<bool>) -> Self {
-        Self { name, note, abbrev }
+        Self {
+            name,
+            note,
+            abbrev,
+            parent: None,
+            rule: None,
+            clone_dir: None,
+            exec: None,
+            color: None,
+            order: None,
+            hidden: None,
+        }
     }
 
     pub fn new(name: String) -> Self {
@@ -25,17 +59,61 @@ impl Group {
     pub fn is_abbrev(&self) -> bool {
         self.abbrev.unwrap_or(false)
     }
+
+    pub fn is_smart(&self) -> bool {
+        self.rule.is_some()
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.unwrap_or(false)
+    }
+
+    /// returns true if the group has nothing but its name and its place in the tree, such groups are
+    /// deleted automatically when they become empty (`auto_delete_group`).
+    /// The smart groups and the groups with the settings (e.g., `color=red`) are not bare.
+    pub fn is_bare(&self) -> bool {
+        self.note.is_empty() && !self.is_abbrev() && !self.is_smart() && self.settings().is_empty()
+    }
+
+    /// returns the settings of the group as `KEY=VALUE` pairs (e.g., `color=red order=1`).
+    pub fn settings(&self) -> Vec<String> {
+        let mut result = vec![];
+        if let Some(dir) = &self.clone_dir {
+            result.push(format!("clone-dir={}", dir));
+        }
+        if let Some(exec) = &self.exec {
+            result.push(format!("exec=\"{}\"", exec));
+        }
+        if let Some(color) = &self.color {
+            result.push(format!("color={}", color));
+        }
+        if let Some(order) = self.order {
+            result.push(format!("order={}", order));
+        }
+        if self.is_hidden() {
+            result.push(String::from("hidden"));
+        }
+        result
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Relation {
     pub id: String,
     pub group: String,
+    /// the position of the repository in the group, the relations without it follow the others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
 }
 
 impl Relation {
     pub fn new(id: String, group: String) -> Self {
-        Self { id, group }
+        Self { id, group, position: None }
+    }
+
+    /// the sort key of the relations in a group.
+    pub fn position_key(&self) -> u32 {
+        self.position.unwrap_or(u32::MAX)
     }
 }
 
@@ -68,7 +146,15 @@ impl RepositoryEntry {
         } else if self.is_last_access() {
             r.last_access_string(&config)
         } else {
-            "".to_string()
+            match self {
+                RepositoryEntry::Language => r.project.language.clone().unwrap_or_default(),
+                RepositoryEntry::Build => r.project.build.clone().unwrap_or_default(),
+                RepositoryEntry::Test => r.project.test.clone().unwrap_or_default(),
+                RepositoryEntry::Docs => r.project.docs.clone().unwrap_or_default(),
+                RepositoryEntry::Owners => r.project.owners.join(", "),
+                RepositoryEntry::Tags => r.tags.join(", "),
+                _ => "".to_string(),
+            }
         }
     }
 }
@@ -79,12 +165,48 @@ pub struct RepositoryWithGroups {
     pub groups: Vec<Group>,
 }
 
+/// the repository with the names of its groups, given to the plugins and the clients of `rrh serve`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RepositorySummary {
+    pub id: String,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    pub groups: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Project::is_empty")]
+    pub project: Project,
+}
+
+impl RepositorySummary {
+    pub fn new(r: Repository, db: &dyn RefDB) -> Self {
+        Self {
+            groups: db.find_groups_of(&r.id)
+                .map(|gs| gs.into_iter().map(|g| g.name).collect())
+                .unwrap_or_default(),
+            id: r.id,
+            path: r.path,
+            description: r.description,
+            tags: r.tags,
+            project: r.project,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Repository {
     pub id: String,
     pub path: PathBuf,
     pub description: Option<String>,
     pub last_access: Option<SystemTime>,
+    /// the lightweight labels, `KEY` or `KEY=VALUE` (e.g., `archived`, `lang=rust`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Project::is_empty")]
+    pub project: Project,
+    /// the pinned repositories are shown first in `list`, `recent` and `pick`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
 impl Repository {
@@ -95,6 +217,9 @@ impl Repository {
                 path,
                 description,
                 last_access: m.accessed().ok(),
+                tags: vec![],
+                project: Project::default(),
+                pinned: false,
             }
         } else {
             Self {
@@ -102,6 +227,9 @@ impl Repository {
                 path,
                 description,
                 last_access: None,
+                tags: vec![],
+                project: Project::default(),
+                pinned: false,
             }
         }
     }
@@ -122,6 +250,120 @@ impl Repository {
         }
         self.last_access
     }
+
+    /// returns true if the id, the path, the description, or the project metadata contains
+    /// the given keyword (in the lower case).
+    pub fn matches(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        let contains = |s: &str| s.to_lowercase().contains(&keyword);
+        contains(&self.id)
+            || contains(&self.path.to_string_lossy())
+            || self.description.as_deref().map(contains).unwrap_or(false)
+            || self.tags.iter().any(|t| contains(t))
+            || self.project.values().iter().any(|v| contains(v))
+    }
+
+    /// returns true if the repository has the given tag, `KEY` matches both `KEY` and `KEY=VALUE`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag || (!tag.contains('=') && tag_key(t) == tag))
+    }
+
+    /// returns true if the repository has all of the given tags.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|t| self.has_tag(t))
+    }
+
+    /// adds the tag, and replaces the value of the same key for `KEY=VALUE`.
+    pub fn add_tag(&mut self, tag: &str) {
+        if tag.contains('=') {
+            self.tags.retain(|t| tag_key(t) != tag_key(tag));
+        }
+        if !self.tags.iter().any(|t| t == tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// removes the tags matched with the given tag (see `has_tag`), and returns true if removed.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|t| !(t == tag || (!tag.contains('=') && tag_key(t) == tag)));
+        len != self.tags.len()
+    }
+
+    /// reads `.rrh.toml` in the repository, and caches its values into the project metadata.
+    /// Returns true if the metadata was changed.
+    pub fn refresh_project(&mut self) -> Result<bool> {
+        match Project::load(&self.path) {
+            Some(Ok(p)) => {
+                let merged = self.project.merge(p);
+                let changed = merged != self.project;
+                self.project = merged;
+                Ok(changed)
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(false),
+        }
+    }
+}
+
+/// the project metadata of the repository, stored in the database or declared in `.rrh.toml`
+/// of the repository, e.g., `test = "cargo test"`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Project {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<String>,
+}
+
+/// the file name of the project metadata in the repository.
+pub const PROJECT_FILE: &str = ".rrh.toml";
+
+impl Project {
+    pub fn is_empty(&self) -> bool {
+        *self == Project::default()
+    }
+
+    /// loads `.rrh.toml` in the given directory, or returns `None` if it does not exist.
+    pub fn load(dir: &Path) -> Option<Result<Project>> {
+        let path = dir.join(PROJECT_FILE);
+        if !path.is_file() {
+            return None;
+        }
+        Some(match std::fs::read_to_string(&path) {
+            Ok(data) => toml::from_str(&data).map_err(|e| RrhError::Toml(format!("{}: {}", path.display(), e))),
+            Err(e) => Err(RrhError::IO(e)),
+        })
+    }
+
+    /// returns the metadata overwritten by the given one (only its declared fields).
+    pub fn merge(&self, other: Project) -> Project {
+        Project {
+            language: other.language.or(self.language.clone()),
+            build: other.build.or(self.build.clone()),
+            test: other.test.or(self.test.clone()),
+            docs: other.docs.or(self.docs.clone()),
+            owners: if other.owners.is_empty() { self.owners.clone() } else { other.owners },
+        }
+    }
+
+    fn values(&self) -> Vec<&str> {
+        let mut result = [&self.language, &self.build, &self.test, &self.docs].into_iter()
+            .filter_map(|v| v.as_deref())
+            .collect::<Vec<_>>();
+        result.extend(self.owners.iter().map(|o| o.as_str()));
+        result
+    }
+}
+
+fn tag_key(tag: &str) -> &str {
+    tag.split_once('=').map(|(key, _)| key).unwrap_or(tag)
 }
 
 fn update_last_access(r: &mut Repository) {
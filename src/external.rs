@@ -1,55 +1,310 @@
 use std::env;
-use std::path::PathBuf;
-use std::process::Command;
-
-use crate::cli::{Result, RrhError};
-
-pub(crate) fn find_and_execute(args: Vec<String>) -> Result<bool> {
-    let command = &args[0].clone();
-    let args = args[1..].to_vec();
-    if let Ok(paths) = env::var("PATH") {
-        for path in paths.split(":") {
-            if let Some(cmd) = find_command(PathBuf::from(path), command.to_string()) {
-                if let Err(e) = execute(cmd, args.clone()) {
-                    return Err(e);
-                } else {
-                    return Ok(false);
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use clap::CommandFactory;
+use serde::{Deserialize, Serialize};
+
+use crate::alias::AliasManager;
+use crate::cli::{CliOpts, Result, RrhError};
+use crate::commands::select_repositories;
+use crate::config::Context;
+use crate::entities::{Repository, RepositorySummary};
+use crate::utils;
+
+/// the version of the plugin protocol, given by `RRH_PLUGIN_API`.
+/// The plugins (`rrh2-<name>` or `rrh-<name>` on PATH) are run with the following contract.
+///   - the environment variables: `RRH_PLUGIN_API`, `RRH_PLUGIN_NAME`, `RRH_CONFIG`,
+///     `RRH_DATABASE`, and `RRH_EXECUTABLE` (the path of rrh itself).
+///   - stdin: the JSON of `Selection`. The leading `-g GROUP`, `-r REPO_ID` and `--select EXPR`
+///     of the arguments select the repositories, and all repositories are given without them.
+///   - stdout and stderr are passed through, and the exit status is the status of rrh.
+///   - `--rrh-describe`: prints the JSON of `Description` (or a line of the description).
+pub(crate) const PLUGIN_API: &str = "1";
+
+const PREFIXES: [&str; 2] = ["rrh2-", "rrh-"];
+const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Plugin {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct Description {
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) version: Option<String>,
+    #[serde(default)]
+    pub(crate) api: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Selection {
+    api: String,
+    selected: bool,
+    repositories: Vec<RepositorySummary>,
+}
+
+/// runs the plugin of the first argument, or returns `UnknownCommand` with the similar names.
+pub(crate) fn find_and_execute(context: &Context, args: Vec<String>) -> Result<bool> {
+    find_and_execute_in(context, args, &search_path())
+}
+
+fn find_and_execute_in(context: &Context, args: Vec<String>, dirs: &[PathBuf]) -> Result<bool> {
+    let name = args[0].clone();
+    let plugin = match find_plugins_in(dirs).into_iter().find(|p| p.name == name) {
+        Some(p) => p,
+        None => return Err(RrhError::UnknownCommand(name.clone(), suggestions(context, &name, dirs))),
+    };
+    match select_repositories(context, args[1..].to_vec()) {
+        Ok((repos, args)) => plugin.execute(context, repos, args).map(|_| false),
+        Err(e) => Err(e),
+    }
+}
+
+/// returns the subcommands, aliases, and plugins similar to the given name.
+fn suggestions(context: &Context, name: &str, dirs: &[PathBuf]) -> Vec<String> {
+    let mut candidates = CliOpts::command()
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set())
+        .map(|s| s.get_name().to_string())
+        .collect::<Vec<_>>();
+    candidates.extend(context.config.iterator().map(|a| a.name));
+    candidates.extend(find_plugins_in(dirs).into_iter().map(|p| p.name));
+    similar_names(name, candidates)
+}
+
+fn similar_names(name: &str, candidates: Vec<String>) -> Vec<String> {
+    let threshold = (name.chars().count() / 3).max(2);
+    let mut result = candidates.into_iter()
+        .map(|c| {
+            let distance = if c.starts_with(name) { 0 } else { utils::edit_distance(name, &c) };
+            (distance, c)
+        })
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect::<Vec<_>>();
+    result.sort();
+    result.dedup_by(|a, b| a.1 == b.1);
+    result.into_iter().take(MAX_SUGGESTIONS).map(|(_, c)| c).collect()
+}
+
+/// finds the plugins on PATH, the former directories of PATH take precedence.
+pub(crate) fn find_plugins() -> Vec<Plugin> {
+    find_plugins_in(&search_path())
+}
+
+fn search_path() -> Vec<PathBuf> {
+    env::var_os("PATH")
+        .map(|p| env::split_paths(&p).collect::<Vec<_>>())
+        .unwrap_or_default()
+}
+
+/// finds the plugins in the given directories, the former directories take precedence.
+fn find_plugins_in(dirs: &[PathBuf]) -> Vec<Plugin> {
+    let mut result: Vec<Plugin> = vec![];
+    for dir in dirs {
+        let mut entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>(),
+            Err(_) => continue,
+        };
+        entries.sort();
+        for prefix in PREFIXES {
+            for path in entries.iter().filter(|p| is_executable(p)) {
+                let name = path.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_prefix(prefix));
+                if let Some(name) = name {
+                    if !name.is_empty() && !result.iter().any(|p| p.name == name) {
+                        result.push(Plugin { name: name.to_string(), path: path.clone() });
+                    }
                 }
             }
         }
     }
-    Ok(false)
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    result
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
 }
 
-fn execute(cmd: PathBuf, args: Vec<String>) -> Result<()> {
-    let result = Command::new(cmd.to_str().unwrap())
-        .args(args.clone())
-        .output();
-    match result {
-        Ok(r) => {
-            if !r.stdout.is_empty() {
-                print!("{}", String::from_utf8_lossy(&r.stdout));
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+impl Plugin {
+    pub(crate) fn execute(&self, context: &Context, repos: Option<Vec<Repository>>, args: Vec<String>) -> Result<()> {
+        let selection = match build_selection(context, repos) {
+            Ok(s) => s,
+            Err(e) => return Err(e),
+        };
+        log::info!("plugin {}: {} {}", self.name, self.path.display(), args.join(" "));
+        let mut command = self.command(context);
+        let mut child = match command.args(&args).stdin(Stdio::piped()).spawn() {
+            Ok(c) => c,
+            Err(e) => return Err(RrhError::IO(e)),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            // the plugins not reading stdin close the pipe, so the broken pipe is not the error.
+            match stdin.write_all(selection.as_bytes()) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(RrhError::IO(e)),
+                _ => {}
             }
-            if r.status.success() {
-                Ok(())
-            } else {
-                Err(RrhError::ExternalCommand(r.status, format!("{:?} {}", cmd, args.clone().join(" "))))
+        }
+        match child.wait() {
+            Ok(s) if s.success() => Ok(()),
+            Ok(s) => Err(RrhError::ExternalCommand(s, format!("{} {}", self.path.display(), args.join(" ")))),
+            Err(e) => Err(RrhError::IO(e)),
+        }
+    }
+
+    /// runs the plugin with `--rrh-describe`, and kills it if it does not finish in a few seconds.
+    pub(crate) fn describe(&self, context: &Context) -> Option<Description> {
+        let mut child = self.command(context)
+            .arg("--rrh-describe")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        // reads stdout while waiting, or the plugin writing more than the pipe buffer blocks.
+        let mut stdout = child.stdout.take()?;
+        let reader = std::thread::spawn(move || {
+            let mut data = vec![];
+            stdout.read_to_end(&mut data).map(|_| data)
+        });
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(s)) if s.success() => break,
+                Ok(None) if start.elapsed() < DESCRIBE_TIMEOUT => std::thread::sleep(Duration::from_millis(20)),
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    log::warn!("{}: --rrh-describe failed", self.path.display());
+                    return None;
+                }
             }
-        },
-        Err(e) => Err(RrhError::IO(e)),
+        }
+        match reader.join() {
+            Ok(Ok(data)) => parse_description(&String::from_utf8_lossy(&data)),
+            _ => None,
+        }
     }
-}
 
-fn find_command(base: PathBuf, command: String) -> Option<PathBuf> {
-    if base.is_dir() {
-        let file1 = base.join(format!("rrh2-{}", command));
-        if file1.exists() {
-            return Some(file1);
+    fn command(&self, context: &Context) -> Command {
+        let mut command = Command::new(&self.path);
+        command.env("RRH_PLUGIN_API", PLUGIN_API)
+            .env("RRH_PLUGIN_NAME", &self.name)
+            .env("RRH_DATABASE", &context.config.database_path);
+        if let Some(from) = &context.config.from {
+            command.env("RRH_CONFIG", from);
         }
-        let file2 = base.join(format!("rrh-{}", command));
-        if file2.exists() {
-            return Some(file2);
+        if let Ok(exe) = env::current_exe() {
+            command.env("RRH_EXECUTABLE", exe);
         }
+        command
+    }
+}
+
+fn parse_description(output: &str) -> Option<Description> {
+    let output = output.trim();
+    if output.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<Description>(output) {
+        Ok(d) => Some(d),
+        Err(_) => output.lines().next().map(|line| Description {
+            description: line.to_string(),
+            ..Default::default()
+        }),
+    }
+}
+
+fn build_selection(context: &Context, repos: Option<Vec<Repository>>) -> Result<String> {
+    let selected = repos.is_some();
+    let repos = match repos {
+        Some(repos) => repos,
+        None => match context.db.repositories() {
+            Ok(repos) => repos,
+            Err(e) => return Err(e),
+        },
+    };
+    let repositories = repos.into_iter()
+        .map(|r| RepositorySummary::new(r, context.db.as_ref()))
+        .collect();
+    let selection = Selection { api: PLUGIN_API.to_string(), selected, repositories };
+    serde_json::to_string(&selection).map_err(RrhError::Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TempDir;
+
+    #[test]
+    fn test_parse_description() {
+        let d = parse_description("{\"description\": \"sync repos\", \"version\": \"1.0\"}").unwrap();
+        assert_eq!(d.description, "sync repos");
+        assert_eq!(d.version, Some(String::from("1.0")));
+        let d = parse_description("sync repos\nusage: ...").unwrap();
+        assert_eq!(d.description, "sync repos");
+        assert!(parse_description("  ").is_none());
+    }
+
+    #[test]
+    fn test_similar_names() {
+        let candidates = ["list", "group", "grlist", "clone", "exec"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(similar_names("lsit", candidates), vec!["list"]);
+        let candidates = ["list", "group", "grlist"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(similar_names("gr", candidates), vec!["grlist", "group"]);
+        assert!(similar_names("unknown", vec![String::from("list")]).is_empty());
+        assert_eq!(utils::edit_distance("kitten", "sitting"), 3);
     }
-    None
-}
\ No newline at end of file
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_and_execute() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("plugin");
+        let script = dir.join("rrh2-check");
+        std::fs::write(&script, "#!/bin/sh\ntest \"$RRH_PLUGIN_API\" = 1 && grep -q '\"selected\":true' && test \"$1\" = arg\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let dirs = vec![dir.to_path_buf()];
+
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(find_and_execute_in(&context, args(&["check", "-r", "fibonacci", "arg"]), &dirs).is_ok());
+        assert!(matches!(find_and_execute_in(&context, args(&["check", "arg"]), &dirs), Err(RrhError::ExternalCommand(_, _))));
+        assert!(matches!(find_and_execute_in(&context, args(&["chekc"]), &dirs), Err(RrhError::UnknownCommand(_, s)) if s.contains(&String::from("check"))));
+
+        let script = dir.join("rrh2-verbose");
+        std::fs::write(&script, "#!/bin/sh\necho verbose plugin\nhead -c 200000 /dev/zero | tr '\\0' x\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let plugin = Plugin { name: String::from("verbose"), path: script };
+        assert_eq!(plugin.describe(&context).unwrap().description, "verbose plugin");
+    }
+
+    #[test]
+    fn test_build_selection() {
+        let context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let json = build_selection(&context, None).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["api"], PLUGIN_API);
+        assert_eq!(v["selected"], false);
+        assert_eq!(v["repositories"][0]["id"], "fibonacci");
+        assert_eq!(v["repositories"][0]["groups"][0], "no-group");
+    }
+}
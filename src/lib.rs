@@ -0,0 +1,136 @@
+//! rrh2 manages the git repositories in the local machine by the groups.
+//!
+//! The library gives the same database and operations as the `rrh2` command.
+//!   - [`Context`] loads the configuration and the database (of the profile),
+//!   - [`RefDB`] queries and [`Database`] mutates the repositories, the groups and their relations,
+//!   - [`selector::Selector`] selects the repositories by the query expressions,
+//!   - [`perform`] runs the operations as the command line, and
+//!   - [`RrhError`] reports the errors.
+//!
+//! ```
+//! use rrh2::{Context, RefDB};
+//!
+//! let context = Context::new_with_path("testdata/config.json".into()).unwrap();
+//! let repo = context.db.find_repository("fibonacci").unwrap();
+//! let groups = context.db.find_groups_of(&repo.id).unwrap();
+//! assert_eq!(groups[0].name, "no-group");
+//! ```
+use std::path::PathBuf;
+
+use cli::RrhError::*;
+
+use crate::alias::AliasManager;
+use crate::commands::*;
+
+mod alias;
+pub mod cli;
+mod commands;
+pub mod config;
+pub mod db;
+pub mod entities;
+mod external;
+mod logger;
+pub mod selector;
+mod terminal;
+mod utils;
+
+pub use cli::{CliOpts, Result, RrhError};
+pub use config::{Config, Context};
+pub use db::{Database, RefDB};
+pub use entities::{Group, Relation, Repository};
+
+use cli::RrhCommand;
+
+/// loads the context by `--config-file`, or `--profile` (and its defaults).
+pub(crate) fn load_context(config_file: Option<PathBuf>, profile: Option<String>) -> Result<Context> {
+    match config_file {
+        Some(config) => Context::new_with_path(config),
+        None => Context::new_with_profile(&config::profile::selected(profile)),
+    }
+}
+
+/// initializes the diagnostics by `-v`, `--quiet`, and `--log-file` of the given options.
+pub fn init_logger(opts: &CliOpts) {
+    logger::init(opts.verbose, opts.quiet, opts.log_file.clone());
+}
+
+/// runs the command of the given options, e.g., `CliOpts::try_parse_from(["rrh2", "list"])`,
+/// and stores the database if the command updates it.
+pub fn perform(opts: CliOpts) -> Result<()> {
+    if let Some(RrhCommand::Setup(c)) = &opts.command {
+        return perform_setup(opts.config_file.clone(), c.clone()).map(|_| ());
+    }
+    if let Some(RrhCommand::Profile(c)) = &opts.command {
+        return perform_profile(opts.profile.clone(), c.clone()).map(|_| ());
+    }
+    if let Some(RrhCommand::Serve(c)) = opts.command {
+        return perform_serve(opts.config_file, opts.profile, c).map(|_| ());
+    }
+    let mut context = match load_context(opts.config_file.clone(), opts.profile.clone()) {
+        Ok(c) => c,
+        Err(e) => return Err(e),
+    };
+    let store_flag = match opts.command {
+        Some(RrhCommand::Add(c)) => perform_add(&mut context, c),
+        Some(RrhCommand::Alias(c)) => perform_alias(&mut context, c),
+        Some(RrhCommand::Clone(c)) => perform_clone(&mut context, c),
+        Some(RrhCommand::Config(c)) => perform_config(&mut context, c),
+        Some(RrhCommand::Find(c)) => perform_find(&context, c),
+        Some(RrhCommand::Exec(c)) => perform_exec(&mut context, c),
+        Some(RrhCommand::Export(c)) => perform_export(&mut context, c),
+        Some(RrhCommand::Group(c)) => perform_group(&mut context, c),
+        Some(RrhCommand::Import(c)) => perform_import(&mut context, c),
+        Some(RrhCommand::Init(c)) => perform_init(&mut context, c),
+        Some(RrhCommand::List(c)) => perform_list(&mut context, c),
+        Some(RrhCommand::Open(c)) => perform_open(&mut context, c),
+        Some(RrhCommand::Path(c)) => perform_path(&context, c),
+        Some(RrhCommand::Pick(c)) => perform_pick(&context, c),
+        Some(RrhCommand::Pin(c)) => perform_pin(&mut context, c, true),
+        Some(RrhCommand::Plugins(c)) => perform_plugins(&context, c),
+        Some(RrhCommand::Project(c)) => perform_project(&mut context, c),
+        Some(RrhCommand::Prune(c)) => perform_prune(&mut context, c),
+        Some(RrhCommand::Repository(c)) => perform_repository(&mut context, c),
+        Some(RrhCommand::Recent(c)) => perform_recent(&context, c),
+        Some(RrhCommand::Rename(c)) => perform_rename(&mut context, c),
+        Some(RrhCommand::Remove(c)) => perform_remove(&mut context, c),
+        Some(RrhCommand::Run(c)) => perform_run(&mut context, c),
+        Some(RrhCommand::Tag(c)) => perform_tag(&mut context, c),
+        Some(RrhCommand::Tui(c)) => perform_tui(&mut context, c),
+        Some(RrhCommand::Unpin(c)) => perform_pin(&mut context, c, false),
+        Some(RrhCommand::Setup(_)) | Some(RrhCommand::Profile(_)) | Some(RrhCommand::Serve(_)) => Ok(false),
+        Some(RrhCommand::Complete(c)) => perform_complete(&context, c),
+        None => find_alias_or_external_command(&mut context, opts.args),
+    };
+    match store_flag {
+        Ok(true) => context.store(),
+        Ok(false) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn find_alias_or_external_command(
+    context: &mut Context,
+    args: Vec<String>,
+) -> Result<bool> {
+    if args.is_empty() {
+        return Err(RrhError::Arguments(String::from("no command given, see \"rrh --help\"")));
+    }
+    if let Some(a) = context.config.find(args[0].clone()) {
+        if let Err(e) = a.execute(context, args[1..].to_vec()) {
+            return Err(e)
+        }
+    } else {
+        return external::find_and_execute(context, args);
+    }
+    Ok(false)
+}
+
+/// returns the exit status of the command for the given error.
+pub fn exit_status(e: &RrhError) -> i32 {
+    match e {
+        AmbiguousRepository(_, _) => 2,
+        ExternalCommand(s, _) => s.code().unwrap_or(1),
+        Arrays(v) if v.len() == 1 => exit_status(&v[0]),
+        _ => 1,
+    }
+}
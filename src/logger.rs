@@ -0,0 +1,106 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// the level of the records written into the log file.
+const FILE_LEVEL: LevelFilter = LevelFilter::Debug;
+
+/// writes the diagnostics into stderr (never into stdout), and into the log file if given.
+struct Logger {
+    level: LevelFilter,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level || (self.file.is_some() && metadata.level() <= FILE_LEVEL)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= self.level {
+            eprintln!("{}: {}", label(record.level()), record.args());
+        }
+        if let Some(file) = &self.file {
+            if record.level() <= self.level.max(FILE_LEVEL) {
+                if let Ok(mut f) = file.lock() {
+                    let _ = writeln!(f, "{} {:<5} {}: {}",
+                        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%z"),
+                        record.level(), record.target(), record.args());
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut f) = file.lock() {
+                let _ = f.flush();
+            }
+        }
+    }
+}
+
+fn label(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+fn level_of(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// initializes the logger by `-v`, `-vv`, `--quiet`, and `--log-file` (or `RRH_LOG_FILE`).
+pub(crate) fn init(verbose: u8, quiet: bool, log_file: Option<PathBuf>) {
+    let level = level_of(verbose, quiet);
+    let file = log_file
+        .or_else(|| env::var("RRH_LOG_FILE").ok().filter(|s| !s.is_empty()).map(PathBuf::from))
+        .and_then(|path| match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => Some(f),
+            Err(e) => {
+                eprintln!("warning: {}: cannot open the log file ({})", path.display(), e);
+                None
+            }
+        });
+    let max_level = if file.is_some() { level.max(FILE_LEVEL) } else { level };
+    let logger = Logger { level, file: file.map(Mutex::new) };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// runs the given function, and logs its elapsed time in the debug level.
+pub(crate) fn timed<T, F: FnOnce() -> T>(label: &str, f: F) -> T {
+    let start = Instant::now();
+    let result = f();
+    log::debug!("{} ({:.2?})", label, start.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_of() {
+        assert_eq!(level_of(0, false), LevelFilter::Warn);
+        assert_eq!(level_of(2, false), LevelFilter::Debug);
+        assert_eq!(level_of(5, false), LevelFilter::Trace);
+        assert_eq!(level_of(0, true), LevelFilter::Error);
+    }
+}
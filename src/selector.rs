@@ -0,0 +1,564 @@
+//! the selection query language shared by the commands (`--select EXPR`).
+//!
+//! ```text
+//! expr   := or
+//! or     := and ("or" and)*
+//! and    := unary ("and"? unary)*          (the adjacent terms are joined by and)
+//! unary  := ("not" | "!") unary | "(" expr ")" | term
+//! term   := FIELD ":" GLOB                 (group, id, path, desc, lang, remote, host, owner)
+//!         | FIELD "~" REGEX                (the above fields and tag)
+//!         | "tag:" TAG                     (KEY matches KEY and KEY=VALUE)
+//!         | "accessed" ("<" | ">") DURATION (e.g., 30m, 12h, 7d, 2w)
+//!         | "dirty" | "clean" | "all"
+//!         | NAME                           (the repository id or the group name)
+//! ```
+//! The values including the spaces are quoted by `"` or `'` (e.g., `desc:"my tools"`),
+//! and the balanced parentheses in a value are a part of it (e.g., `id~^(rrh|tabled)`).
+//!
+//! ```
+//! use rrh2::{Context, selector::Selector};
+//!
+//! let context = Context::new_with_path("testdata/config.json".into()).unwrap();
+//! let selector = Selector::parse("group:no-group and not id~^hello").unwrap();
+//! let repos = selector.select(context.db.as_ref()).unwrap();
+//! assert_eq!(repos[0].id, "fibonacci");
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use git_url_parse::GitUrl;
+use regex::Regex;
+
+use crate::cli::{Result, RrhError};
+use crate::db::RefDB;
+use crate::entities::{Group, Repository};
+use crate::utils;
+
+/// the parsed selection expression.
+#[derive(Debug, Clone)]
+pub enum Selector {
+    All,
+    /// the repository of the id, or the repositories of the group.
+    Name(String),
+    Tag(String),
+    Field(Field, Regex),
+    /// selects the repositories accessed within (`true`) or before (`false`) the duration.
+    Accessed(bool, Duration),
+    /// selects the dirty (`true`) or the clean (`false`) repositories.
+    Dirty(bool),
+    Not(Box<Selector>),
+    And(Box<Selector>, Box<Selector>),
+    Or(Box<Selector>, Box<Selector>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Group,
+    Id,
+    Path,
+    Description,
+    Language,
+    Tag,
+    /// the urls of the git remotes.
+    Remote,
+    /// the hosts of the git remotes (e.g., `github.com`).
+    Host,
+    /// the owners or the organizations of the git remotes.
+    Owner,
+}
+
+impl Selector {
+    pub fn parse(expr: &str) -> Result<Selector> {
+        let tokens = match tokenize(expr) {
+            Ok(tokens) => tokens,
+            Err(message) => return Err(RrhError::Selector(expr.to_string(), message)),
+        };
+        let mut parser = Parser { tokens, pos: 0 };
+        match parser.parse_or() {
+            Ok(s) if parser.pos == parser.tokens.len() => Ok(s),
+            Ok(_) => Err(RrhError::Selector(expr.to_string(), format!("unexpected {}", parser.tokens[parser.pos]))),
+            Err(message) => Err(RrhError::Selector(expr.to_string(), message)),
+        }
+    }
+
+    /// builds the selector from the given tags (`--tag`) and expression (`--select`),
+    /// and returns `None` if neither of them is given.
+    pub fn from_options(tags: &[String], expr: Option<&str>) -> Result<Option<Selector>> {
+        let mut result = tags.iter().map(|t| Selector::Tag(t.clone())).collect::<Vec<_>>();
+        if let Some(expr) = expr {
+            match Selector::parse(expr) {
+                Ok(s) => result.push(s),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(result.into_iter().reduce(|a, b| Selector::And(Box::new(a), Box::new(b))))
+    }
+
+    pub fn matches(&self, repo: &Repository, db: &dyn RefDB) -> bool {
+        self.matches_in(repo, db, &mut vec![])
+    }
+
+    /// `visiting` holds the smart groups whose rules are under evaluation, for breaking the cycles
+    /// of the rules referring to each other.
+    fn matches_in(&self, repo: &Repository, db: &dyn RefDB, visiting: &mut Vec<String>) -> bool {
+        use Selector::*;
+        match self {
+            All => true,
+            Name(name) => &repo.id == name || group_names(repo, db, visiting).contains(name),
+            Tag(tag) => repo.has_tag(tag),
+            Field(field, regex) => field.values(repo, db, visiting).iter().any(|v| regex.is_match(v)),
+            Accessed(within, duration) => match repo.last_access.and_then(|t| SystemTime::now().duration_since(t).ok()) {
+                Some(age) => (age < *duration) == *within,
+                None => false,
+            },
+            Dirty(dirty) => match utils::is_dirty(&repo.path) {
+                Ok(d) => d == *dirty,
+                Err(e) => {
+                    log::warn!("{}: {}", repo.id, e);
+                    false
+                }
+            },
+            Not(s) => !s.matches_in(repo, db, visiting),
+            And(a, b) => a.matches_in(repo, db, visiting) && b.matches_in(repo, db, visiting),
+            Or(a, b) => a.matches_in(repo, db, visiting) || b.matches_in(repo, db, visiting),
+        }
+    }
+
+    /// returns the matched repositories in the database.
+    pub fn select(&self, db: &dyn RefDB) -> Result<Vec<Repository>> {
+        match db.repositories() {
+            Ok(repos) => Ok(repos.into_iter().filter(|r| self.matches(r, db)).collect()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// returns the given ids followed by the ids of the other repositories matched with the expression (`--select`).
+pub(crate) fn with_selected_ids(db: &dyn RefDB, ids: &[String], expr: Option<&str>) -> Result<Vec<String>> {
+    let mut result = ids.to_vec();
+    match Selector::from_options(&[], expr) {
+        Ok(Some(s)) => match s.select(db) {
+            Ok(repos) => result.extend(repos.into_iter().map(|r| r.id).filter(|id| !ids.contains(id))),
+            Err(e) => return Err(e),
+        },
+        Ok(None) => {}
+        Err(e) => return Err(e),
+    }
+    Ok(result)
+}
+
+/// narrows the repositories by the selector.
+/// Without the target repositories and groups, the selector selects from all repositories.
+pub(crate) fn narrow(db: &dyn RefDB, repos: Vec<Repository>, no_targets: bool, selector: Option<&Selector>) -> Result<Vec<Repository>> {
+    match selector {
+        None => Ok(repos),
+        Some(s) if no_targets => s.select(db),
+        Some(s) => Ok(repos.into_iter().filter(|r| s.matches(r, db)).collect()),
+    }
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name {
+            "group" => Some(Field::Group),
+            "id" => Some(Field::Id),
+            "path" => Some(Field::Path),
+            "desc" | "description" => Some(Field::Description),
+            "lang" | "language" => Some(Field::Language),
+            "tag" => Some(Field::Tag),
+            "remote" => Some(Field::Remote),
+            "host" => Some(Field::Host),
+            "owner" => Some(Field::Owner),
+            _ => None,
+        }
+    }
+
+    fn values(&self, repo: &Repository, db: &dyn RefDB, visiting: &mut Vec<String>) -> Vec<String> {
+        match self {
+            Field::Group => group_names(repo, db, visiting),
+            Field::Id => vec![repo.id.clone()],
+            Field::Path => vec![utils::expand_home(&repo.path.to_string_lossy()).to_string_lossy().to_string()],
+            Field::Description => vec![repo.description.clone().unwrap_or_default()],
+            Field::Language => repo.project.language.iter().cloned().collect(),
+            Field::Tag => repo.tags.clone(),
+            Field::Remote => utils::remote_urls(&repo.path),
+            Field::Host => utils::remote_urls(&repo.path).iter()
+                .filter_map(|url| GitUrl::parse(url).ok())
+                .filter_map(|gu| gu.host)
+                .collect(),
+            Field::Owner => utils::remote_urls(&repo.path).iter()
+                .filter_map(|url| GitUrl::parse(url).ok())
+                .filter_map(|gu| gu.owner.or(gu.organization))
+                .collect(),
+        }
+    }
+}
+
+/// returns the names of the groups of the repository (including the smart groups whose rules
+/// match it) and their ancestors, since selecting a group selects the repositories of its descendants.
+fn group_names(repo: &Repository, db: &dyn RefDB, visiting: &mut Vec<String>) -> Vec<String> {
+    let mut groups = db.find_relation_with_repository(&repo.id).into_iter()
+        .map(|r| r.group)
+        .collect::<Vec<_>>();
+    for group in db.groups().unwrap_or_default() {
+        if group.is_smart() && !groups.contains(&group.name) && rule_matches(db, &group, repo, visiting) {
+            groups.push(group.name);
+        }
+    }
+    let mut result: Vec<String> = vec![];
+    for group in groups {
+        let ancestors = db.find_ancestors(&group).into_iter().map(|g| g.name);
+        for name in std::iter::once(group).chain(ancestors) {
+            if !result.contains(&name) {
+                result.push(name);
+            }
+        }
+    }
+    result
+}
+
+/// returns true if the rule of the smart group matches the repository.
+/// The broken rules are warned and match nothing, and the groups under evaluation (`visiting`)
+/// match nothing for breaking the cycles of the rules.
+/// The results evaluated from the top (not inside the other rules) are kept in the rule cache
+/// of the database, so that a rule (e.g., `dirty`) is evaluated once for each repository.
+pub(crate) fn rule_matches(db: &dyn RefDB, group: &Group, repo: &Repository, visiting: &mut Vec<String>) -> bool {
+    let rule = match &group.rule {
+        Some(rule) if !visiting.contains(&group.name) => rule,
+        _ => return false,
+    };
+    let cache = db.rule_cache();
+    let key = (group.name.clone(), repo.id.clone());
+    if let Some(matched) = cache.and_then(|c| c.members.borrow().get(&key).copied()) {
+        return matched;
+    }
+    let selector = match cache.and_then(|c| c.rules.borrow().get(&group.name).cloned()) {
+        Some(s) => s,
+        None => {
+            let s = match Selector::parse(rule) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    log::warn!("{}: {}", group.name, e);
+                    None
+                }
+            };
+            if let Some(c) = cache {
+                c.rules.borrow_mut().insert(group.name.clone(), s.clone());
+            }
+            s
+        }
+    };
+    let top = visiting.is_empty();
+    visiting.push(group.name.clone());
+    let matched = selector.is_some_and(|s| s.matches_in(repo, db, visiting));
+    visiting.pop();
+    if let Some(c) = cache.filter(|_| top) {
+        c.members.borrow_mut().insert(key, matched);
+    }
+    matched
+}
+
+/// the parsed rules of the smart groups and their memberships, held by the database until it is updated.
+/// The copies of the database do not share the cache.
+#[derive(Debug, Default)]
+pub struct RuleCache {
+    rules: RefCell<HashMap<String, Option<Selector>>>,
+    members: RefCell<HashMap<(String, String), bool>>,
+}
+
+impl RuleCache {
+    pub fn clear(&self) {
+        self.rules.borrow_mut().clear();
+        self.members.borrow_mut().clear();
+    }
+}
+
+impl Clone for RuleCache {
+    fn clone(&self) -> Self {
+        RuleCache::default()
+    }
+}
+
+impl PartialEq for RuleCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RuleCache {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Bang,
+    /// the word and whether it contains the quoted part.
+    Word(String, bool),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+            Token::Bang => write!(f, "\"!\""),
+            Token::Word(w, _) => write!(f, "\"{}\"", w),
+        }
+    }
+}
+
+/// splits the expression into the tokens. The parentheses in a word (e.g., `id~(a|b)`)
+/// belong to the word while they are balanced.
+fn tokenize(expr: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    // the current word, whether it is quoted, and the depth of the parentheses in it.
+    let mut word: Option<(String, bool, usize)> = None;
+    let mut chars = expr.chars();
+    while let Some(c) = chars.next() {
+        match (c, word.as_mut()) {
+            ('"' | '\'', _) => {
+                let (mut w, _, depth) = word.take().unwrap_or_default();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(q) => w.push(q),
+                        None => return Err(String::from("unterminated quote")),
+                    }
+                }
+                word = Some((w, true, depth));
+            }
+            ('(', None) => tokens.push(Token::Open),
+            ('!', None) => tokens.push(Token::Bang),
+            ('(', Some((w, _, depth))) => {
+                w.push(c);
+                *depth += 1;
+            }
+            (')', Some((w, _, depth))) if *depth > 0 => {
+                w.push(c);
+                *depth -= 1;
+            }
+            (')', _) => {
+                if let Some((w, q, _)) = word.take() {
+                    tokens.push(Token::Word(w, q));
+                }
+                tokens.push(Token::Close);
+            }
+            (c, _) if c.is_whitespace() => {
+                if let Some((w, q, _)) = word.take() {
+                    tokens.push(Token::Word(w, q));
+                }
+            }
+            (c, Some((w, _, _))) => w.push(c),
+            (c, None) => word = Some((c.to_string(), false, 0)),
+        }
+    }
+    if let Some((w, q, _)) = word {
+        tokens.push(Token::Word(w, q));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w, false)) if w == keyword)
+    }
+
+    fn parse_or(&mut self) -> std::result::Result<Selector, String> {
+        let mut left = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Selector::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> std::result::Result<Selector, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            if self.is_keyword("and") {
+                self.pos += 1;
+            } else if self.peek().is_none() || self.peek() == Some(&Token::Close) || self.is_keyword("or") {
+                break;
+            }
+            let right = self.parse_unary()?;
+            left = Selector::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> std::result::Result<Selector, String> {
+        let token = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err(String::from("unexpected end of the expression")),
+        };
+        self.pos += 1;
+        match token {
+            Token::Bang => self.parse_unary().map(|s| Selector::Not(Box::new(s))),
+            Token::Word(w, false) if w == "not" => self.parse_unary().map(|s| Selector::Not(Box::new(s))),
+            Token::Open => {
+                let s = self.parse_or()?;
+                match self.peek() {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(s)
+                    }
+                    _ => Err(String::from("\")\" is expected")),
+                }
+            }
+            Token::Close => Err(String::from("unexpected \")\"")),
+            Token::Word(w, false) if w == "and" || w == "or" => Err(format!("unexpected \"{}\"", w)),
+            Token::Word(w, quoted) => parse_term(&w, quoted),
+        }
+    }
+}
+
+fn parse_term(word: &str, quoted: bool) -> std::result::Result<Selector, String> {
+    let name_end = word.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(word.len());
+    let (name, rest) = word.split_at(name_end);
+    let mut rest = rest.chars();
+    let op = rest.next();
+    let value = rest.as_str();
+    match (name, op) {
+        ("all", None) if !quoted => Ok(Selector::All),
+        ("dirty", None) if !quoted => Ok(Selector::Dirty(true)),
+        ("clean", None) if !quoted => Ok(Selector::Dirty(false)),
+        ("accessed", Some(op @ ('<' | '>'))) => parse_duration(value).map(|d| Selector::Accessed(op == '<', d)),
+        ("tag", Some(':')) => Ok(Selector::Tag(value.to_string())),
+        (name, Some(op @ (':' | '~'))) if !name.is_empty() => {
+            let field = match Field::parse(name) {
+                Some(f) => f,
+                None => return Err(format!("{}: unknown field", name)),
+            };
+            let pattern = match (op, field) {
+                (':', Field::Path) => glob_to_regex(&utils::expand_home(value).to_string_lossy()),
+                (':', _) => glob_to_regex(value),
+                _ => value.to_string(),
+            };
+            Regex::new(&pattern).map(|r| Selector::Field(field, r)).map_err(|e| e.to_string())
+        }
+        _ => Ok(Selector::Name(word.to_string())),
+    }
+}
+
+/// parses the duration, such as `30s`, `30m`, `12h`, `7d` and `2w`.
+fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("{}: unknown unit of the duration", unit)),
+    };
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(seconds)) {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => Err(format!("{}: invalid duration", value)),
+    }
+}
+
+/// converts the glob (`*` and `?`) into the anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut result = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => result.push_str(".*"),
+            '?' => result.push('.'),
+            c => result.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    result.push('$');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::Context;
+
+    fn select_ids(context: &Context, expr: &str) -> Vec<String> {
+        let selector = Selector::parse(expr).unwrap();
+        selector.select(context.db.as_ref()).unwrap().into_iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(Selector::parse("all").unwrap(), Selector::All));
+        assert!(matches!(Selector::parse("not dirty").unwrap(), Selector::Not(s) if matches!(*s, Selector::Dirty(true))));
+        assert!(matches!(Selector::parse("a b or c").unwrap(), Selector::Or(a, _) if matches!(*a, Selector::And(_, _))));
+        assert!(matches!(Selector::parse("a and (b or c)").unwrap(), Selector::And(_, b) if matches!(*b, Selector::Or(_, _))));
+        assert!(matches!(Selector::parse("accessed<2w").unwrap(), Selector::Accessed(true, d) if d.as_secs() == 14 * 24 * 3600));
+        assert!(matches!(Selector::parse("desc:\"my tools\"").unwrap(), Selector::Field(Field::Description, r) if r.is_match("my tools")));
+        assert!(matches!(Selector::parse("'and'").unwrap(), Selector::Name(n) if n == "and"));
+        assert!(matches!(Selector::parse("host:github.com owner:tamada").unwrap(), Selector::And(a, _) if matches!(*a, Selector::Field(Field::Host, _))));
+        assert!(Selector::parse("").is_err());
+        assert!(Selector::parse("(group:work").is_err());
+        assert!(Selector::parse("group:work)").is_err());
+        assert!(Selector::parse("unknown:x").is_err());
+        assert!(Selector::parse("accessed<7y").is_err());
+        assert!(Selector::parse("accessed<99999999999999999w").is_err());
+        assert!(matches!(Selector::parse("(id~(a|b))").unwrap(), Selector::Field(Field::Id, r) if r.is_match("b")));
+        assert!(Selector::parse("id~[").is_err());
+        assert!(Selector::parse("desc:\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let r = Regex::new(&glob_to_regex("~/src/*.rs?")).unwrap();
+        assert!(r.is_match("~/src/main.rs1"));
+        assert!(!r.is_match("/home/src/main.rs"));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut repo = context.db.find_repository("helloworld").unwrap();
+        repo.add_tag("archived");
+        context.db.update_repository(repo.id.clone(), repo).unwrap();
+
+        assert_eq!(select_ids(&context, "group:no-* and not tag:archived"), vec!["fibonacci"]);
+        assert_eq!(select_ids(&context, "id~^h or fibonacci"), vec!["fibonacci", "helloworld"]);
+        assert_eq!(select_ids(&context, "path:testdata/hello*"), vec!["helloworld"]);
+        assert_eq!(select_ids(&context, "!no-group"), Vec::<String>::new());
+
+        let selector = Selector::from_options(&[String::from("archived")], Some("group:no-group")).unwrap().unwrap();
+        let repos = narrow(context.db.as_ref(), vec![], true, Some(&selector)).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert!(Selector::from_options(&[], None).unwrap().is_none());
+
+        let ids = with_selected_ids(context.db.as_ref(), &[String::from("helloworld")], Some("group:no-group")).unwrap();
+        assert_eq!(ids, vec!["helloworld", "fibonacci"]);
+        assert!(with_selected_ids(context.db.as_ref(), &[], Some("(group:work")).is_err());
+    }
+
+    #[test]
+    fn test_rule_cache() {
+        let mut context = Context::new_with_path(PathBuf::from("testdata/config.json")).unwrap();
+        let mut g = Group::new("hello".into());
+        g.rule = Some(String::from("id~^hello"));
+        context.db.register_group(g).unwrap();
+        assert_eq!(select_ids(&context, "group:hello"), vec!["helloworld"]);
+        let cache = context.db.rule_cache().unwrap();
+        assert!(cache.rules.borrow().contains_key("hello"));
+        assert_eq!(cache.members.borrow().get(&(String::from("hello"), String::from("fibonacci"))), Some(&false));
+
+        let mut g = context.db.find_group("hello").unwrap();
+        g.rule = Some(String::from("id~^fib"));
+        context.db.update_group("hello".into(), g).unwrap();
+        assert!(context.db.rule_cache().unwrap().members.borrow().is_empty());
+        assert_eq!(select_ids(&context, "group:hello"), vec!["fibonacci"]);
+    }
+}
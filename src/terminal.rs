@@ -52,6 +52,30 @@ pub fn to_string_in_column_with(values: Vec<String>, cols: usize, spacer: usize)
     lines.join("\n")
 }
 
+/// the names of the colors and their ANSI codes.
+const COLORS: [(&str, u8); 8] = [
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+];
+
+pub fn is_color_name(name: &str) -> bool {
+    COLORS.iter().any(|(n, _)| *n == name)
+}
+
+/// returns the text in the given color by the ANSI escape sequence, or the text as is for unknown colors.
+pub fn colorize(text: &str, color: &str) -> String {
+    match COLORS.iter().find(|(n, _)| *n == color) {
+        Some((_, code)) => format!("\x1b[{}m{}\x1b[0m", code, text),
+        None => text.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,4 +97,11 @@ mod tests {
         let r2 = to_string_in_column_with(v1.clone(), 125, 2);
         assert_eq!(r2.trim(), "macOS             Linux             Windows           Go                VisualStudioCode  JetBrains");
     }
+
+    #[test]
+    fn test_colorize() {
+        assert_eq!(colorize("team", "red"), "\x1b[31mteam\x1b[0m");
+        assert_eq!(colorize("team", "unknown"), "team");
+        assert!(is_color_name("cyan"));
+    }
 }
@@ -1,3 +1,8 @@
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Result, RrhError};
+use crate::logger;
+
 pub fn format_humanize<T>(count: T, singular: &str, plural: &str) -> String
 where
     T: std::fmt::Display,
@@ -7,4 +12,103 @@ where
     } else {
         format!("{} {}", count, plural)
     }
-}
\ No newline at end of file
+}
+
+/// expands the leading `~` of the given path into the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    if path == "~" {
+        dirs::home_dir().unwrap_or(PathBuf::from("."))
+    } else if let (Some(rest), Some(home)) = (path.strip_prefix("~/"), dirs::home_dir()) {
+        home.join(rest)
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// returns the Levenshtein distance between the given strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// returns the branch name and the short status lines of the git repository at the given path.
+pub fn git_status(path: &Path) -> Result<Vec<String>> {
+    let label = format!("git status: {}", path.display());
+    logger::timed(&label, || git_status_impl(path))
+}
+
+/// returns whether the working tree of the git repository at the given path has any changes.
+pub fn is_dirty(path: &Path) -> Result<bool> {
+    let repo = git2::Repository::open(path).map_err(RrhError::Git)?;
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    repo.statuses(Some(&mut opts))
+        .map(|s| !s.is_empty())
+        .map_err(RrhError::Git)
+}
+
+/// returns the urls of the remotes of the git repository at the given path.
+pub fn remote_urls(path: &Path) -> Vec<String> {
+    let repo = match git2::Repository::open(path) {
+        Ok(r) => r,
+        Err(_) => return vec![],
+    };
+    let names = match repo.remotes() {
+        Ok(names) => names,
+        Err(_) => return vec![],
+    };
+    names.iter()
+        .flatten()
+        .filter_map(|name| repo.find_remote(name).ok())
+        .filter_map(|remote| remote.url().map(String::from))
+        .collect()
+}
+
+fn git_status_impl(path: &Path) -> Result<Vec<String>> {
+    let repo = git2::Repository::open(path).map_err(RrhError::Git)?;
+    let mut result = vec![];
+    if let Ok(head) = repo.head() {
+        if let Some(name) = head.shorthand() {
+            result.push(format!("On branch {}", name));
+        }
+    }
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut opts)).map_err(RrhError::Git)?;
+    if statuses.is_empty() {
+        result.push(String::from("nothing to commit, working tree clean"));
+    }
+    for entry in statuses.iter() {
+        result.push(format!("{} {}", status_mark(entry.status()), entry.path().unwrap_or("")));
+    }
+    Ok(result)
+}
+
+fn status_mark(s: git2::Status) -> &'static str {
+    if s.is_wt_new() {
+        "??"
+    } else if s.is_index_new() {
+        "A "
+    } else if s.is_index_modified() || s.is_index_renamed() || s.is_index_typechange() {
+        "M "
+    } else if s.is_wt_modified() || s.is_wt_renamed() || s.is_wt_typechange() {
+        " M"
+    } else if s.is_index_deleted() {
+        "D "
+    } else if s.is_wt_deleted() {
+        " D"
+    } else if s.is_conflicted() {
+        "UU"
+    } else {
+        "  "
+    }
+}